@@ -23,8 +23,10 @@ async fn main() {
 
 fn initialize_mailgun(api_key: &str, domain: &str) {
     let mailgun_client = Mailgun {
-        api_key: api_key.to_string(),
+        api_key: api_key.into(),
         domain: domain.to_string(),
+        region: MailgunRegion::US,
+        ..Default::default()
     };
 
     MAILGUN_CLIENT
@@ -47,7 +49,7 @@ async fn send_html(recipient: &str) {
         let mailgun_client = client.lock().unwrap();
 
         match mailgun_client
-            .async_send(MailgunRegion::US, &sender, message, None)
+            .async_send(&sender, message, None)
             .await
         {
             Ok(_) => {
@@ -74,13 +76,15 @@ async fn send_template(recipient: &str, key: &str, domain: &str) {
         ..Default::default()
     };
     let client = Mailgun {
-        api_key: String::from(key),
+        api_key: key.into(),
         domain: String::from(domain),
+        region: MailgunRegion::US,
+        ..Default::default()
     };
     let sender = EmailAddress::name_address("no-reply", "no-reply@huatuo.xyz");
 
     match client
-        .async_send(MailgunRegion::US, &sender, message, None)
+        .async_send(&sender, message, None)
         .await
     {
         Ok(_) => {
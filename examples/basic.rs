@@ -24,12 +24,14 @@ fn send_html(recipient: &str, key: &str, domain: &str) {
         ..Default::default()
     };
     let client = Mailgun {
-        api_key: String::from(key),
+        api_key: key.into(),
         domain: String::from(domain),
+        region: MailgunRegion::US,
+        ..Default::default()
     };
     let sender = EmailAddress::name_address("no-reply", "no-reply@huatuo.xyz");
 
-    match client.send(MailgunRegion::US, &sender, message, None) {
+    match client.send(&sender, message, None) {
         Ok(_) => {
             println!("successful");
         }
@@ -51,12 +53,14 @@ fn send_template(recipient: &str, key: &str, domain: &str) {
         ..Default::default()
     };
     let client = Mailgun {
-        api_key: String::from(key),
+        api_key: key.into(),
         domain: String::from(domain),
+        region: MailgunRegion::US,
+        ..Default::default()
     };
     let sender = EmailAddress::name_address("no-reply", "no-reply@huatuo.xyz");
 
-    match client.send(MailgunRegion::US, &sender, message, None) {
+    match client.send(&sender, message, None) {
         Ok(_) => {
             println!("successful");
         }
@@ -90,13 +94,15 @@ fn send_with_attachment(recipient: &str, key: &str, domain: &str) {
     }
 
     let client = Mailgun {
-        api_key: String::from(key),
+        api_key: key.into(),
         domain: String::from(domain),
+        region: MailgunRegion::US,
+        ..Default::default()
     };
 
     let sender = EmailAddress::name_address("no-reply", "no-reply@huatuo.xyz");
 
-    match client.send(MailgunRegion::US, &sender, message, Some(attachments)) {
+    match client.send(&sender, message, Some(attachments)) {
         Ok(_) => {
             println!("successful");
         }
@@ -130,13 +136,15 @@ fn send_with_inline_attachment(recipient: &str, key: &str, domain: &str) {
     );
 
     let client = Mailgun {
-        api_key: String::from(key),
+        api_key: key.into(),
         domain: String::from(domain),
+        region: MailgunRegion::US,
+        ..Default::default()
     };
 
     let sender = EmailAddress::name_address("no-reply", "no-reply@huatuo.xyz");
 
-    match client.send(MailgunRegion::US, &sender, message, Some(attachments)) {
+    match client.send(&sender, message, Some(attachments)) {
         Ok(_) => {
             println!("successful");
         }
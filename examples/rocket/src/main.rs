@@ -1,9 +1,17 @@
 #[macro_use]
 extern crate rocket;
 
+use rocket::data::{Data, ToByteUnit};
+use rocket::http::{ContentType, Status};
 use rocket::serde::{json::Json, Deserialize, Serialize};
+use rocket::State;
 
-use mailgun_rs::{EmailAddress, Mailgun, Message};
+use mailgun_rs::webhook_receiver::header::{HeaderMap, CONTENT_TYPE};
+use mailgun_rs::webhook_receiver::WebhookReceiver;
+use mailgun_rs::webhooks::{MemoryTokenStore, Verifier, WebhookEvent};
+use mailgun_rs::{EmailAddress, Mailgun, MailgunRegion, Message};
+use std::sync::Arc;
+use std::time::Duration;
 
 use dotenv::dotenv;
 use std::env;
@@ -71,12 +79,14 @@ fn send_mail_confirmation(order: &Json<Order<'_>>) {
     };
 
     let client = Mailgun {
-        api_key: String::from(api_key),
+        api_key: api_key.into(),
         domain: String::from(domain),
+        region: MailgunRegion::US,
+        ..Default::default()
     };
     let sender = EmailAddress::name_address("no-reply", "no-reply@huatuo.xyz");
 
-    match client.send(MailgunRegion::US, &sender, message, None) {
+    match client.send(&sender, message, None) {
         Ok(_) => {
             println!("successful");
         }
@@ -104,13 +114,15 @@ async fn send_mail_confirmation_async(order: &Json<Order<'_>>) {
     };
 
     let client = Mailgun {
-        api_key: String::from(api_key),
+        api_key: api_key.into(),
         domain: String::from(domain),
+        region: MailgunRegion::US,
+        ..Default::default()
     };
     let sender = EmailAddress::name_address("no-reply", "no-reply@huatuo.xyz");
 
     match client
-        .async_send(MailgunRegion::US, &sender, message, None)
+        .async_send(&sender, message, None)
         .await
     {
         Ok(_) => {
@@ -122,10 +134,54 @@ async fn send_mail_confirmation_async(order: &Json<Order<'_>>) {
     }
 }
 
+#[post("/webhooks/mailgun", data = "<body>")]
+pub async fn mailgun_webhook(
+    content_type: Option<&ContentType>,
+    body: Data<'_>,
+    receiver: &State<WebhookReceiver>,
+) -> Status {
+    let body = match body.open(1.mebibytes()).into_bytes().await {
+        Ok(body) if body.is_complete() => body.into_inner(),
+        _ => return Status::PayloadTooLarge,
+    };
+
+    let mut headers = HeaderMap::new();
+    if let Some(value) = content_type.and_then(|c| c.to_string().parse().ok()) {
+        headers.insert(CONTENT_TYPE, value);
+    }
+
+    match receiver.receive(&headers, &body) {
+        Ok(_) => Status::Ok,
+        Err(err) => {
+            println!("Rejected webhook: {err}");
+            Status::from_code(err.status_code()).unwrap_or(Status::BadRequest)
+        }
+    }
+}
+
+fn webhook_receiver() -> WebhookReceiver {
+    let signing_key = env::var("MAILGUN_WEBHOOK_SIGNING_KEY")
+        .expect("MAILGUN_WEBHOOK_SIGNING_KEY not set");
+
+    WebhookReceiver::new(Verifier::builder().signing_key(signing_key).build())
+        .deduplicate(
+            Arc::new(MemoryTokenStore::new()),
+            Duration::from_secs(24 * 60 * 60),
+        )
+        .on(WebhookEvent::PermanentFail, |event| {
+            println!("Bounced: {:?}", event.recipient);
+        })
+        .on(WebhookEvent::Complained, |event| {
+            println!("Complained: {:?}", event.recipient);
+        })
+}
+
 #[launch]
 fn rocket() -> _ {
     dotenv().ok();
     rocket::build()
+        .manage(webhook_receiver())
         .mount("/", routes![hello])
         .mount("/", routes![save_order, save_order_async])
+        .mount("/", routes![mailgun_webhook])
 }
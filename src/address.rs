@@ -0,0 +1,440 @@
+use crate::EmailAddress;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+use thiserror::Error;
+
+const MAX_LOCAL_PART_LEN: usize = 64;
+const MAX_LABEL_LEN: usize = 63;
+const MAX_DOMAIN_LEN: usize = 253;
+const MAX_ADDRESS_LEN: usize = 254;
+/// Bytes of UTF-8 per RFC 2047 encoded word, keeping each word within the
+/// 75 character limit once base64-encoded.
+const ENCODED_WORD_BYTES: usize = 45;
+
+/// Why a string is not a valid email address.
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum AddressError {
+    #[error("email address is empty")]
+    Empty,
+
+    #[error("email address has no `@`")]
+    MissingAt,
+
+    #[error("email address is longer than {MAX_ADDRESS_LEN} characters")]
+    TooLong,
+
+    #[error("the part before `@` is empty")]
+    EmptyLocalPart,
+
+    #[error("the part before `@` is longer than {MAX_LOCAL_PART_LEN} characters")]
+    LocalPartTooLong,
+
+    #[error("`{0}` is not allowed before `@` unless quoted")]
+    InvalidLocalChar(char),
+
+    #[error("the part before `@` has a leading, trailing or doubled `.`")]
+    MisplacedDot,
+
+    #[error("the quoted part before `@` is not closed or has text after it")]
+    UnterminatedQuote,
+
+    #[error("the part after `@` is empty")]
+    EmptyDomain,
+
+    #[error("the domain is longer than {MAX_DOMAIN_LEN} characters")]
+    DomainTooLong,
+
+    #[error("`{0}` is not a valid domain label")]
+    InvalidDomainLabel(String),
+
+    #[error("`{0}` is not a valid address literal")]
+    InvalidAddressLiteral(String),
+
+    #[error("`{0}` is not a valid internationalized domain")]
+    InvalidIdn(String),
+
+    #[error("`<` is not closed by `>`")]
+    UnterminatedAngle,
+}
+
+/// `atext` of RFC 5322: characters allowed in an unquoted local part.
+fn is_atext(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-/=?^_`{|}~".contains(c)
+}
+
+/// Splits an addr-spec at the `@` after the local part, which may be quoted
+/// and contain `@` itself.
+fn split_addr_spec(spec: &str) -> Result<(&str, &str), AddressError> {
+    if spec.starts_with('"') {
+        let mut escaped = false;
+        for (i, c) in spec.char_indices().skip(1) {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => {
+                    let (local, rest) = spec.split_at(i + 1);
+                    return rest.strip_prefix('@').map(|domain| (local, domain)).ok_or(
+                        if rest.is_empty() {
+                            AddressError::MissingAt
+                        } else {
+                            AddressError::UnterminatedQuote
+                        },
+                    );
+                }
+                _ => {}
+            }
+        }
+        Err(AddressError::UnterminatedQuote)
+    } else {
+        spec.rsplit_once('@').ok_or(AddressError::MissingAt)
+    }
+}
+
+fn check_local_part(local: &str) -> Result<(), AddressError> {
+    if local.is_empty() {
+        return Err(AddressError::EmptyLocalPart);
+    }
+    if local.len() > MAX_LOCAL_PART_LEN {
+        return Err(AddressError::LocalPartTooLong);
+    }
+    if let Some(quoted) = local.strip_prefix('"') {
+        // `split_addr_spec` found the closing quote, so only the characters
+        // in between are left to check.
+        let mut escaped = false;
+        for c in quoted[..quoted.len() - 1].chars() {
+            let printable = c == ' ' || c.is_ascii_graphic();
+            if escaped {
+                if !printable && c != '\t' {
+                    return Err(AddressError::InvalidLocalChar(c));
+                }
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if !printable || c == '"' {
+                return Err(AddressError::InvalidLocalChar(c));
+            }
+        }
+        return Ok(());
+    }
+    if let Some(c) = local.chars().find(|&c| c != '.' && !is_atext(c)) {
+        return Err(AddressError::InvalidLocalChar(c));
+    }
+    if local.split('.').any(str::is_empty) {
+        return Err(AddressError::MisplacedDot);
+    }
+    Ok(())
+}
+
+/// Validates the domain, returning it lowercased and in punycode.
+fn normalize_domain(domain: &str) -> Result<String, AddressError> {
+    if domain.is_empty() {
+        return Err(AddressError::EmptyDomain);
+    }
+    if let Some(literal) = domain
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+    {
+        let valid = match literal.get(..5) {
+            Some(tag) if tag.eq_ignore_ascii_case("IPv6:") => {
+                literal[5..].parse::<Ipv6Addr>().is_ok()
+            }
+            _ => literal.parse::<Ipv4Addr>().is_ok(),
+        };
+        return if valid {
+            Ok(domain.to_ascii_lowercase())
+        } else {
+            Err(AddressError::InvalidAddressLiteral(domain.to_string()))
+        };
+    }
+
+    let ascii =
+        idna::domain_to_ascii(domain).map_err(|_| AddressError::InvalidIdn(domain.to_string()))?;
+    if ascii.len() > MAX_DOMAIN_LEN {
+        return Err(AddressError::DomainTooLong);
+    }
+    for label in ascii.split('.') {
+        let valid = !label.is_empty()
+            && label.len() <= MAX_LABEL_LEN
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+        if !valid {
+            return Err(AddressError::InvalidDomainLabel(label.to_string()));
+        }
+    }
+    Ok(ascii)
+}
+
+/// Reads a display name, removing the quotes and escapes of a quoted one.
+fn unquote_name(name: &str) -> String {
+    match name
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+    {
+        Some(quoted) => {
+            let mut unquoted = String::with_capacity(quoted.len());
+            let mut chars = quoted.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => unquoted.extend(chars.next()),
+                    c => unquoted.push(c),
+                }
+            }
+            unquoted
+        }
+        None => name.to_string(),
+    }
+}
+
+/// Decodes `B` and `Q` encoded words of RFC 2047 whose charset is UTF-8 or
+/// ASCII, and returns `(text, bytes consumed)`.
+fn decode_encoded_word(word: &str) -> Option<(String, usize)> {
+    let rest = word.strip_prefix("=?")?;
+    let (charset, rest) = rest.split_once('?')?;
+    let (encoding, rest) = rest.split_once('?')?;
+    let end = rest.find("?=")?;
+    let text = &rest[..end];
+    // RFC 2231 allows a language suffix, e.g. `UTF-8*en`.
+    let charset = charset.split('*').next().unwrap_or_default();
+    if !charset.eq_ignore_ascii_case("utf-8") && !charset.eq_ignore_ascii_case("us-ascii") {
+        return None;
+    }
+    let bytes = match encoding {
+        "B" | "b" => STANDARD.decode(text).ok()?,
+        "Q" | "q" => {
+            let mut bytes = Vec::with_capacity(text.len());
+            let mut iter = text.bytes();
+            while let Some(b) = iter.next() {
+                match b {
+                    b'_' => bytes.push(b' '),
+                    b'=' => {
+                        let hex = [iter.next()?, iter.next()?];
+                        let hex = std::str::from_utf8(&hex).ok()?;
+                        bytes.push(u8::from_str_radix(hex, 16).ok()?);
+                    }
+                    b => bytes.push(b),
+                }
+            }
+            bytes
+        }
+        _ => return None,
+    };
+    let consumed = word.len() - (rest.len() - end - 2);
+    Some((String::from_utf8(bytes).ok()?, consumed))
+}
+
+/// Replaces RFC 2047 encoded words in a display name with their text.
+/// Whitespace between adjacent encoded words is dropped, as the RFC requires.
+fn decode_display_name(name: &str) -> String {
+    let mut decoded = String::with_capacity(name.len());
+    let mut rest = name;
+    let mut after_word = false;
+    while let Some(start) = rest.find("=?") {
+        let (before, candidate) = rest.split_at(start);
+        match decode_encoded_word(candidate) {
+            Some((text, consumed)) => {
+                if !(after_word && before.trim().is_empty()) {
+                    decoded.push_str(before);
+                }
+                decoded.push_str(&text);
+                rest = &candidate[consumed..];
+                after_word = true;
+            }
+            None => {
+                decoded.push_str(before);
+                decoded.push_str("=?");
+                rest = &candidate[2..];
+                after_word = false;
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Writes a display name so it survives in a header: plain words as is,
+/// other ASCII names quoted, and anything else as RFC 2047 encoded words.
+pub(crate) fn write_display_name(f: &mut fmt::Formatter, name: &str) -> fmt::Result {
+    if !name.contains("=?") && name.chars().all(|c| c == ' ' || is_atext(c)) {
+        return f.write_str(name);
+    }
+    if name.chars().all(|c| c == ' ' || c.is_ascii_graphic()) {
+        f.write_str("\"")?;
+        for c in name.chars() {
+            if c == '"' || c == '\\' {
+                f.write_str("\\")?;
+            }
+            write!(f, "{c}")?;
+        }
+        return f.write_str("\"");
+    }
+
+    let mut chunk_start = 0;
+    let mut first = true;
+    let mut write_word = |f: &mut fmt::Formatter, chunk: &str| {
+        let separator = if first { "" } else { " " };
+        first = false;
+        write!(f, "{separator}=?UTF-8?B?{}?=", STANDARD.encode(chunk))
+    };
+    for (i, c) in name.char_indices() {
+        if i + c.len_utf8() - chunk_start > ENCODED_WORD_BYTES {
+            write_word(f, &name[chunk_start..i])?;
+            chunk_start = i;
+        }
+    }
+    write_word(f, &name[chunk_start..])
+}
+
+/// Splits a header value at the commas that separate addresses, skipping
+/// commas inside quoted names and angle brackets.
+fn split_address_list(list: &str) -> Vec<&str> {
+    let mut entries = Vec::new();
+    let mut start = 0;
+    let mut in_quotes = false;
+    let mut in_angle = false;
+    let mut escaped = false;
+    for (i, c) in list.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            '<' if !in_quotes => in_angle = true,
+            '>' if !in_quotes => in_angle = false,
+            ',' if !in_quotes && !in_angle => {
+                entries.push(&list[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    entries.push(&list[start..]);
+    entries
+}
+
+impl EmailAddress {
+    /// Parses a bare addr-spec such as `jane@example.com` following RFC 5321
+    /// and RFC 5322. The domain is lowercased and converted to punycode; the
+    /// local part is kept as written, since its case may matter.
+    pub fn parse(address: &str) -> Result<Self, AddressError> {
+        let address = address.trim();
+        if address.is_empty() {
+            return Err(AddressError::Empty);
+        }
+        let (local, domain) = split_addr_spec(address)?;
+        check_local_part(local)?;
+        let domain = normalize_domain(domain)?;
+        let address = format!("{local}@{domain}");
+        if address.len() > MAX_ADDRESS_LEN {
+            return Err(AddressError::TooLong);
+        }
+        Ok(EmailAddress::address(&address))
+    }
+
+    /// Parses an address with a display name, e.g. `Jane Doe <jane@example.com>`.
+    pub fn parse_with_name(name: &str, address: &str) -> Result<Self, AddressError> {
+        let mut parsed = EmailAddress::parse(address)?;
+        let name = decode_display_name(&unquote_name(name.trim()));
+        if !name.is_empty() {
+            parsed.name = Some(name);
+        }
+        Ok(parsed)
+    }
+
+    /// Parses a comma-separated list such as a `To` header, e.g.
+    /// `"Doe, Jane" <jane@example.com>, john@example.com`. Display names may
+    /// be RFC 2047 encoded.
+    pub fn parse_list(list: &str) -> Result<Vec<Self>, AddressError> {
+        split_address_list(list)
+            .into_iter()
+            .filter(|entry| !entry.trim().is_empty())
+            .map(str::parse)
+            .collect()
+    }
+}
+
+/// Accepts a bare address or one in `Name <address>` form.
+impl FromStr for EmailAddress {
+    type Err = AddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s.rfind('<') {
+            Some(start) => {
+                let address = s[start + 1..]
+                    .strip_suffix('>')
+                    .ok_or(AddressError::UnterminatedAngle)?;
+                EmailAddress::parse_with_name(&s[..start], address)
+            }
+            None => EmailAddress::parse(s),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_should_normalize_domains_and_report_errors() {
+        assert_eq!(
+            EmailAddress::parse("Jane.Doe@Example.COM"),
+            Ok(EmailAddress::address("Jane.Doe@example.com"))
+        );
+        assert_eq!(
+            EmailAddress::parse("jose@bücher.de"),
+            Ok(EmailAddress::address("jose@xn--bcher-kva.de"))
+        );
+        assert_eq!(
+            EmailAddress::parse(r#""john@home \"doe\""@example.com"#),
+            Ok(EmailAddress::address(r#""john@home \"doe\""@example.com"#))
+        );
+        assert_eq!(
+            "\"Doe, Jane\" <jane@example.com>".parse(),
+            Ok(EmailAddress::name_address("Doe, Jane", "jane@example.com"))
+        );
+
+        assert_eq!(EmailAddress::parse("foo"), Err(AddressError::MissingAt));
+        assert_eq!(
+            EmailAddress::parse("a@b@c"),
+            Err(AddressError::InvalidLocalChar('@'))
+        );
+        assert_eq!(
+            EmailAddress::parse("jane..doe@example.com"),
+            Err(AddressError::MisplacedDot)
+        );
+        assert_eq!(
+            EmailAddress::parse("jane@-example.com"),
+            Err(AddressError::InvalidDomainLabel("-example".to_string()))
+        );
+        assert_eq!(
+            "Jane <jane@example.com".parse::<EmailAddress>(),
+            Err(AddressError::UnterminatedAngle)
+        );
+    }
+
+    #[test]
+    fn display_should_round_trip_through_parse_list() {
+        let recipients = vec![
+            EmailAddress::name_address("Doe, Jane", "jane@example.com"),
+            EmailAddress::name_address("José \"Pepe\" Núñez", "jose@example.com"),
+            EmailAddress::name_address("John Smith", "john@example.com"),
+        ];
+        let joined = recipients
+            .iter()
+            .map(EmailAddress::to_string)
+            .collect::<Vec<String>>()
+            .join(",");
+        assert_eq!(
+            joined,
+            concat!(
+                "\"Doe, Jane\" <jane@example.com>,",
+                "=?UTF-8?B?Sm9zw6kgIlBlcGUiIE7DusOxZXo=?= <jose@example.com>,",
+                "John Smith <john@example.com>"
+            )
+        );
+        assert_eq!(EmailAddress::parse_list(&joined), Ok(recipients));
+    }
+}
@@ -0,0 +1,206 @@
+use crate::secret::Secret;
+use crate::{build_url, get_base_url, Mailgun, SendResult};
+use reqwest::Method;
+use serde::Deserialize;
+use std::io;
+use typed_builder::TypedBuilder;
+
+const DOMAINS_ENDPOINT: &str = "domains";
+const CREDENTIALS_ENDPOINT: &str = "credentials";
+
+/// Length of generated passwords, the most Mailgun accepts.
+const GENERATED_PASSWORD_LEN: usize = 32;
+const PASSWORD_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+/// An SMTP login of the domain. Mailgun never returns passwords.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Credential {
+    pub login: String,
+    #[serde(default)]
+    pub mailbox: String,
+    pub created_at: String,
+    pub size_bytes: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct CredentialList {
+    pub total_count: usize,
+    pub items: Vec<Credential>,
+}
+
+#[derive(TypedBuilder, Debug, PartialEq, Eq, Clone)]
+pub struct NewCredential {
+    /// `user` or `user@your-domain`.
+    #[builder(setter(into))]
+    pub login: String,
+    /// Generated when unset.
+    #[builder(default, setter(strip_option, into))]
+    pub password: Option<Secret>,
+}
+
+/// A login with the password it was just given.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SmtpCredential {
+    pub login: String,
+    pub password: Secret,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct CredentialResponse {
+    pub message: String,
+}
+
+/// A random alphanumeric password from the OS random number generator.
+fn generate_password() -> io::Result<Secret> {
+    let mut password = String::with_capacity(GENERATED_PASSWORD_LEN);
+    let mut bytes = [0u8; 64];
+    while password.len() < GENERATED_PASSWORD_LEN {
+        getrandom::fill(&mut bytes).map_err(|err| io::Error::other(err.to_string()))?;
+        // Dropping bytes past the last whole multiple of the alphabet keeps
+        // every character equally likely.
+        let limit = 256 - 256 % PASSWORD_ALPHABET.len();
+        password.extend(
+            bytes
+                .iter()
+                .filter(|&&byte| (byte as usize) < limit)
+                .map(|&byte| PASSWORD_ALPHABET[byte as usize % PASSWORD_ALPHABET.len()] as char)
+                .take(GENERATED_PASSWORD_LEN - password.len()),
+        );
+    }
+    Ok(Secret::from(password))
+}
+
+fn password_or_generated(password: Option<&Secret>) -> io::Result<Secret> {
+    match password {
+        Some(password) => Ok(password.clone()),
+        None => generate_password(),
+    }
+}
+
+impl Mailgun {
+    fn credentials_url(&self, login: Option<&str>) -> reqwest::Url {
+        let mut segments = vec![DOMAINS_ENDPOINT, &self.domain, CREDENTIALS_ENDPOINT];
+        segments.extend(login);
+        build_url(get_base_url(&self.region), &segments)
+    }
+
+    /// Lists the domain's SMTP logins, skipping the first `skip`.
+    pub fn list_credentials(&self, skip: usize, limit: usize) -> SendResult<CredentialList> {
+        Mailgun::blocking_json(
+            self.blocking_request(Method::GET, self.credentials_url(None))?
+                .query(&[("skip", skip), ("limit", limit)]),
+        )
+    }
+
+    pub fn create_credential(&self, credential: &NewCredential) -> SendResult<SmtpCredential> {
+        let password = password_or_generated(credential.password.as_ref())?;
+        let _: CredentialResponse = Mailgun::blocking_json(
+            self.blocking_request(Method::POST, self.credentials_url(None))?
+                .form(&[
+                    ("login", credential.login.as_str()),
+                    ("password", password.expose_secret()),
+                ]),
+        )?;
+        Ok(SmtpCredential {
+            login: credential.login.clone(),
+            password,
+        })
+    }
+
+    /// Sets a new password for `login`, generating one when `password` is
+    /// `None`.
+    pub fn change_credential_password(
+        &self,
+        login: &str,
+        password: Option<Secret>,
+    ) -> SendResult<SmtpCredential> {
+        let password = password_or_generated(password.as_ref())?;
+        let _: CredentialResponse = Mailgun::blocking_json(
+            self.blocking_request(Method::PUT, self.credentials_url(Some(login)))?
+                .form(&[("password", password.expose_secret())]),
+        )?;
+        Ok(SmtpCredential {
+            login: login.to_string(),
+            password,
+        })
+    }
+
+    pub fn delete_credential(&self, login: &str) -> SendResult<CredentialResponse> {
+        Mailgun::blocking_json(
+            self.blocking_request(Method::DELETE, self.credentials_url(Some(login)))?,
+        )
+    }
+
+    pub async fn async_list_credentials(
+        &self,
+        skip: usize,
+        limit: usize,
+    ) -> SendResult<CredentialList> {
+        Mailgun::async_json(
+            self.async_request(Method::GET, self.credentials_url(None))?
+                .query(&[("skip", skip), ("limit", limit)]),
+        )
+        .await
+    }
+
+    pub async fn async_create_credential(
+        &self,
+        credential: &NewCredential,
+    ) -> SendResult<SmtpCredential> {
+        let password = password_or_generated(credential.password.as_ref())?;
+        let _: CredentialResponse = Mailgun::async_json(
+            self.async_request(Method::POST, self.credentials_url(None))?
+                .form(&[
+                    ("login", credential.login.as_str()),
+                    ("password", password.expose_secret()),
+                ]),
+        )
+        .await?;
+        Ok(SmtpCredential {
+            login: credential.login.clone(),
+            password,
+        })
+    }
+
+    pub async fn async_change_credential_password(
+        &self,
+        login: &str,
+        password: Option<Secret>,
+    ) -> SendResult<SmtpCredential> {
+        let password = password_or_generated(password.as_ref())?;
+        let _: CredentialResponse = Mailgun::async_json(
+            self.async_request(Method::PUT, self.credentials_url(Some(login)))?
+                .form(&[("password", password.expose_secret())]),
+        )
+        .await?;
+        Ok(SmtpCredential {
+            login: login.to_string(),
+            password,
+        })
+    }
+
+    pub async fn async_delete_credential(&self, login: &str) -> SendResult<CredentialResponse> {
+        Mailgun::async_json(self.async_request(Method::DELETE, self.credentials_url(Some(login)))?)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_passwords_should_be_redacted_in_debug() {
+        let credential = SmtpCredential {
+            login: "relay@example.com".to_string(),
+            password: generate_password().unwrap(),
+        };
+        let password = credential.password.expose_secret();
+        assert_eq!(password.len(), GENERATED_PASSWORD_LEN);
+        assert!(password.bytes().all(|byte| byte.is_ascii_alphanumeric()));
+        assert_eq!(
+            format!("{:?}", credential),
+            r#"SmtpCredential { login: "relay@example.com", password: [REDACTED] }"#
+        );
+    }
+}
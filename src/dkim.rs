@@ -0,0 +1,364 @@
+use crate::domains::{DnsRecord, RecordValidity};
+use crate::{build_url, get_base_url, versioned_url, Mailgun, MailgunRegion, SendResult};
+use reqwest::Method;
+use serde::Deserialize;
+use std::thread;
+use std::time::{Duration, Instant};
+use typed_builder::TypedBuilder;
+
+const DKIM_ENDPOINT: &str = "dkim";
+const KEYS_ENDPOINT: &str = "keys";
+const DOMAINS_ENDPOINT: &str = "domains";
+const ACTIVATE_ENDPOINT: &str = "activate";
+const DEACTIVATE_ENDPOINT: &str = "deactivate";
+const AUTHORITY_ENDPOINT: &str = "dkim_authority";
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct DkimKey {
+    pub signing_domain: String,
+    pub selector: String,
+    /// The TXT record to publish for this key.
+    pub dns_record: DnsRecord,
+}
+
+impl DkimKey {
+    pub fn is_active(&self) -> bool {
+        self.dns_record.is_active
+    }
+
+    pub fn is_verified(&self) -> bool {
+        self.dns_record.valid == RecordValidity::Valid
+    }
+}
+
+#[derive(TypedBuilder, Debug, PartialEq, Eq, Clone)]
+pub struct NewDkimKey {
+    #[builder(setter(into))]
+    pub selector: String,
+    /// 1024 or 2048; Mailgun picks when unset.
+    #[builder(default, setter(strip_option))]
+    pub bits: Option<u32>,
+}
+
+impl NewDkimKey {
+    fn params(&self, domain: &str) -> Vec<(&'static str, String)> {
+        let mut params = vec![
+            ("signing_domain", domain.to_string()),
+            ("selector", self.selector.clone()),
+        ];
+        if let Some(bits) = self.bits {
+            params.push(("bits", bits.to_string()));
+        }
+        params
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct DkimKeyResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct DkimAuthorityResponse {
+    pub message: String,
+    pub changed: bool,
+    #[serde(default)]
+    pub sending_dns_records: Vec<DnsRecord>,
+}
+
+#[derive(Deserialize)]
+struct KeyList {
+    items: Vec<DkimKey>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RotationStatus {
+    /// The new key's DNS record has not verified yet; the old keys still sign.
+    AwaitingDns,
+    /// The new key signs and the previous keys are deactivated.
+    Completed,
+}
+
+/// A DKIM key rotation in progress. Publish `key.dns_record`, then call
+/// [`Mailgun::finish_dkim_rotation`] until it completes.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DkimRotation {
+    pub domain: String,
+    pub key: DkimKey,
+    /// Selectors that were active when the rotation started.
+    pub previous_selectors: Vec<String>,
+    pub status: RotationStatus,
+}
+
+impl DkimRotation {
+    fn new(domain: &str, key: DkimKey, existing: &[DkimKey]) -> Self {
+        DkimRotation {
+            domain: domain.to_string(),
+            previous_selectors: existing
+                .iter()
+                .filter(|existing| existing.is_active() && existing.selector != key.selector)
+                .map(|existing| existing.selector.clone())
+                .collect(),
+            key,
+            status: RotationStatus::AwaitingDns,
+        }
+    }
+
+    /// The new key as currently listed, if its record now verifies.
+    fn verified_key(&self, keys: Vec<DkimKey>) -> Option<DkimKey> {
+        keys.into_iter()
+            .find(|key| key.selector == self.key.selector && key.is_verified())
+    }
+}
+
+fn keys_url(region: &MailgunRegion) -> reqwest::Url {
+    versioned_url(region, "v1", &[DKIM_ENDPOINT, KEYS_ENDPOINT])
+}
+
+fn domain_keys_url(region: &MailgunRegion, domain: &str, segments: &[&str]) -> reqwest::Url {
+    let mut path = vec![DOMAINS_ENDPOINT, domain, KEYS_ENDPOINT];
+    path.extend(segments);
+    versioned_url(region, "v4", &path)
+}
+
+impl Mailgun {
+    pub fn list_dkim_keys(&self, domain: &str) -> SendResult<Vec<DkimKey>> {
+        let list: KeyList = Mailgun::blocking_json(
+            self.blocking_request(Method::GET, domain_keys_url(&self.region, domain, &[]))?,
+        )?;
+        Ok(list.items)
+    }
+
+    /// Creates an inactive key; publish its DNS record before activating it.
+    pub fn create_dkim_key(&self, domain: &str, key: &NewDkimKey) -> SendResult<DkimKey> {
+        Mailgun::blocking_json(
+            self.blocking_request(Method::POST, keys_url(&self.region))?
+                .form(&key.params(domain)),
+        )
+    }
+
+    pub fn activate_dkim_key(&self, domain: &str, selector: &str) -> SendResult<DkimKeyResponse> {
+        let url = domain_keys_url(&self.region, domain, &[selector, ACTIVATE_ENDPOINT]);
+        Mailgun::blocking_json(self.blocking_request(Method::PUT, url)?)
+    }
+
+    pub fn deactivate_dkim_key(&self, domain: &str, selector: &str) -> SendResult<DkimKeyResponse> {
+        let url = domain_keys_url(&self.region, domain, &[selector, DEACTIVATE_ENDPOINT]);
+        Mailgun::blocking_json(self.blocking_request(Method::PUT, url)?)
+    }
+
+    pub fn delete_dkim_key(&self, domain: &str, selector: &str) -> SendResult<DkimKeyResponse> {
+        Mailgun::blocking_json(
+            self.blocking_request(Method::DELETE, keys_url(&self.region))?
+                .query(&[("signing_domain", domain), ("selector", selector)]),
+        )
+    }
+
+    /// Signs with the domain's own keys when `own_authority`, or with those
+    /// of its parent domain otherwise.
+    pub fn set_dkim_authority(
+        &self,
+        domain: &str,
+        own_authority: bool,
+    ) -> SendResult<DkimAuthorityResponse> {
+        let url = build_url(
+            get_base_url(&self.region),
+            &[DOMAINS_ENDPOINT, domain, AUTHORITY_ENDPOINT],
+        );
+        Mailgun::blocking_json(
+            self.blocking_request(Method::PUT, url)?
+                .form(&[("self", own_authority.to_string())]),
+        )
+    }
+
+    /// Creates the new key of a rotation. The keys that are active now keep
+    /// signing until [`Mailgun::finish_dkim_rotation`] completes.
+    pub fn start_dkim_rotation(&self, domain: &str, key: &NewDkimKey) -> SendResult<DkimRotation> {
+        let existing = self.list_dkim_keys(domain)?;
+        let key = self.create_dkim_key(domain, key)?;
+        Ok(DkimRotation::new(domain, key, &existing))
+    }
+
+    /// Switches to the new key once its DNS record verifies, and deactivates
+    /// the previous ones. Returns the status, which is also stored in
+    /// `rotation`.
+    pub fn finish_dkim_rotation(&self, rotation: &mut DkimRotation) -> SendResult<RotationStatus> {
+        if rotation.status == RotationStatus::Completed {
+            return Ok(RotationStatus::Completed);
+        }
+        self.verify_domain(&rotation.domain)?;
+        let keys = self.list_dkim_keys(&rotation.domain)?;
+        let key = match rotation.verified_key(keys) {
+            Some(key) => key,
+            None => return Ok(RotationStatus::AwaitingDns),
+        };
+        self.activate_dkim_key(&rotation.domain, &key.selector)?;
+        for selector in &rotation.previous_selectors {
+            self.deactivate_dkim_key(&rotation.domain, selector)?;
+        }
+        rotation.key = key;
+        rotation.status = RotationStatus::Completed;
+        Ok(RotationStatus::Completed)
+    }
+
+    /// Rotates the domain's DKIM key: creates `key`, hands its DNS record to
+    /// `publish`, then checks every `poll_interval` until the record verifies
+    /// and switches keys. Gives up after `timeout` with the rotation still
+    /// [`RotationStatus::AwaitingDns`], so it can be finished later.
+    pub fn rotate_dkim(
+        &self,
+        domain: &str,
+        key: &NewDkimKey,
+        poll_interval: Duration,
+        timeout: Duration,
+        publish: impl FnOnce(&DnsRecord),
+    ) -> SendResult<DkimRotation> {
+        let mut rotation = self.start_dkim_rotation(domain, key)?;
+        publish(&rotation.key.dns_record);
+        let deadline = Instant::now() + timeout;
+        while self.finish_dkim_rotation(&mut rotation)? == RotationStatus::AwaitingDns
+            && Instant::now() + poll_interval <= deadline
+        {
+            thread::sleep(poll_interval);
+        }
+        Ok(rotation)
+    }
+
+    pub async fn async_list_dkim_keys(&self, domain: &str) -> SendResult<Vec<DkimKey>> {
+        let list: KeyList = Mailgun::async_json(
+            self.async_request(Method::GET, domain_keys_url(&self.region, domain, &[]))?,
+        )
+        .await?;
+        Ok(list.items)
+    }
+
+    pub async fn async_create_dkim_key(
+        &self,
+        domain: &str,
+        key: &NewDkimKey,
+    ) -> SendResult<DkimKey> {
+        Mailgun::async_json(
+            self.async_request(Method::POST, keys_url(&self.region))?
+                .form(&key.params(domain)),
+        )
+        .await
+    }
+
+    pub async fn async_activate_dkim_key(
+        &self,
+        domain: &str,
+        selector: &str,
+    ) -> SendResult<DkimKeyResponse> {
+        let url = domain_keys_url(&self.region, domain, &[selector, ACTIVATE_ENDPOINT]);
+        Mailgun::async_json(self.async_request(Method::PUT, url)?).await
+    }
+
+    pub async fn async_deactivate_dkim_key(
+        &self,
+        domain: &str,
+        selector: &str,
+    ) -> SendResult<DkimKeyResponse> {
+        let url = domain_keys_url(&self.region, domain, &[selector, DEACTIVATE_ENDPOINT]);
+        Mailgun::async_json(self.async_request(Method::PUT, url)?).await
+    }
+
+    pub async fn async_delete_dkim_key(
+        &self,
+        domain: &str,
+        selector: &str,
+    ) -> SendResult<DkimKeyResponse> {
+        Mailgun::async_json(
+            self.async_request(Method::DELETE, keys_url(&self.region))?
+                .query(&[("signing_domain", domain), ("selector", selector)]),
+        )
+        .await
+    }
+
+    pub async fn async_set_dkim_authority(
+        &self,
+        domain: &str,
+        own_authority: bool,
+    ) -> SendResult<DkimAuthorityResponse> {
+        let url = build_url(
+            get_base_url(&self.region),
+            &[DOMAINS_ENDPOINT, domain, AUTHORITY_ENDPOINT],
+        );
+        Mailgun::async_json(
+            self.async_request(Method::PUT, url)?
+                .form(&[("self", own_authority.to_string())]),
+        )
+        .await
+    }
+
+    pub async fn async_start_dkim_rotation(
+        &self,
+        domain: &str,
+        key: &NewDkimKey,
+    ) -> SendResult<DkimRotation> {
+        let existing = self.async_list_dkim_keys(domain).await?;
+        let key = self.async_create_dkim_key(domain, key).await?;
+        Ok(DkimRotation::new(domain, key, &existing))
+    }
+
+    /// Like [`Mailgun::finish_dkim_rotation`]. There is no async
+    /// `rotate_dkim`: call this from your runtime's timer until it completes.
+    pub async fn async_finish_dkim_rotation(
+        &self,
+        rotation: &mut DkimRotation,
+    ) -> SendResult<RotationStatus> {
+        if rotation.status == RotationStatus::Completed {
+            return Ok(RotationStatus::Completed);
+        }
+        self.async_verify_domain(&rotation.domain).await?;
+        let keys = self.async_list_dkim_keys(&rotation.domain).await?;
+        let key = match rotation.verified_key(keys) {
+            Some(key) => key,
+            None => return Ok(RotationStatus::AwaitingDns),
+        };
+        self.async_activate_dkim_key(&rotation.domain, &key.selector)
+            .await?;
+        for selector in &rotation.previous_selectors {
+            self.async_deactivate_dkim_key(&rotation.domain, selector)
+                .await?;
+        }
+        rotation.key = key;
+        rotation.status = RotationStatus::Completed;
+        Ok(RotationStatus::Completed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(selector: &str, active: bool, valid: &str) -> DkimKey {
+        serde_json::from_value(serde_json::json!({
+            "signing_domain": "example.com",
+            "selector": selector,
+            "dns_record": {
+                "record_type": "TXT",
+                "name": format!("{}._domainkey.example.com", selector),
+                "value": "k=rsa; p=MIGf",
+                "valid": valid,
+                "is_active": active,
+                "cached": []
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn rotation_should_replace_only_previously_active_keys() {
+        let existing = vec![key("mx2024", true, "valid"), key("old", false, "valid")];
+        let rotation = DkimRotation::new("example.com", key("mx2025", false, "unknown"), &existing);
+        assert_eq!(rotation.previous_selectors, vec!["mx2024".to_string()]);
+
+        assert_eq!(
+            rotation.verified_key(vec![key("mx2025", false, "invalid")]),
+            None
+        );
+        assert!(rotation
+            .verified_key(vec![key("mx2025", false, "valid")])
+            .is_some());
+    }
+}
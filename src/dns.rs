@@ -0,0 +1,317 @@
+use crate::domains::{DnsRecord, Domain};
+use std::collections::HashMap;
+use std::io;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum RecordType {
+    Txt,
+    Mx,
+    Cname,
+}
+
+impl RecordType {
+    fn from_record(record: &DnsRecord) -> Option<Self> {
+        match record.record_type.to_ascii_uppercase().as_str() {
+            "TXT" => Some(RecordType::Txt),
+            "MX" => Some(RecordType::Mx),
+            "CNAME" => Some(RecordType::Cname),
+            _ => None,
+        }
+    }
+}
+
+/// Looks up DNS records, so checks can run against real DNS or a fake zone.
+pub trait DnsResolver {
+    /// Values of the records of `record_type` at `name`: the text of TXT
+    /// records with their strings joined, and the host names of MX and CNAME
+    /// records. A name without such records is an empty list, not an error.
+    fn lookup(&self, record_type: RecordType, name: &str) -> io::Result<Vec<String>>;
+}
+
+impl<F> DnsResolver for F
+where
+    F: Fn(RecordType, &str) -> io::Result<Vec<String>>,
+{
+    fn lookup(&self, record_type: RecordType, name: &str) -> io::Result<Vec<String>> {
+        self(record_type, name)
+    }
+}
+
+/// What a required record is for.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RecordPurpose {
+    Spf,
+    Dkim,
+    Mx,
+    /// The CNAME behind open and click tracking links.
+    Tracking,
+    Other,
+}
+
+impl RecordPurpose {
+    fn of(record: &DnsRecord) -> Self {
+        match RecordType::from_record(record) {
+            Some(RecordType::Txt) if record.value.starts_with("v=spf1") => RecordPurpose::Spf,
+            Some(RecordType::Txt)
+                if record
+                    .name
+                    .as_deref()
+                    .is_some_and(|name| name.contains("._domainkey.")) =>
+            {
+                RecordPurpose::Dkim
+            }
+            Some(RecordType::Mx) => RecordPurpose::Mx,
+            Some(RecordType::Cname) => RecordPurpose::Tracking,
+            _ => RecordPurpose::Other,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum RecordStatus {
+    Ok,
+    /// The record is not published at its name.
+    Missing,
+    /// Other values are published in the record's place, e.g. an old DKIM
+    /// key or an SPF record without Mailgun's servers.
+    Mismatched {
+        found: Vec<String>,
+    },
+    /// The resolver failed, so the record could not be checked.
+    LookupFailed(String),
+    /// Mailgun asked for a record type this check cannot look up, so it
+    /// has to be checked by hand.
+    UnsupportedType(String),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RecordDiff {
+    pub purpose: RecordPurpose,
+    /// Host name the record must be published at.
+    pub name: String,
+    pub record: DnsRecord,
+    pub status: RecordStatus,
+}
+
+/// Result of [`check_records`], one entry per required record.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DnsReport {
+    pub records: Vec<RecordDiff>,
+}
+
+impl DnsReport {
+    /// Whether every required record is published as required.
+    pub fn is_ok(&self) -> bool {
+        self.records
+            .iter()
+            .all(|diff| diff.status == RecordStatus::Ok)
+    }
+
+    /// Records that still need attention.
+    pub fn problems(&self) -> impl Iterator<Item = &RecordDiff> {
+        self.records
+            .iter()
+            .filter(|diff| diff.status != RecordStatus::Ok)
+    }
+}
+
+fn normalize_host(host: &str) -> String {
+    host.trim().trim_end_matches('.').to_ascii_lowercase()
+}
+
+fn without_whitespace(value: &str) -> String {
+    value
+        .trim_matches('"')
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect()
+}
+
+/// SPF mechanisms Mailgun requires, e.g. `include:mailgun.org`, leaving out
+/// the version and the `all` policy which the domain owner may choose.
+fn spf_mechanisms(value: &str) -> Vec<&str> {
+    value
+        .split_whitespace()
+        .filter(|term| *term != "v=spf1" && !is_all_policy(term))
+        .collect()
+}
+
+/// The `all` mechanism with an optional qualifier, e.g. `~all`.
+fn is_all_policy(term: &str) -> bool {
+    let term = term.strip_prefix(['+', '-', '~', '?']).unwrap_or(term);
+    term.eq_ignore_ascii_case("all")
+}
+
+fn matches(purpose: RecordPurpose, required: &str, found: &str) -> bool {
+    match purpose {
+        RecordPurpose::Spf => {
+            found.trim_matches('"').starts_with("v=spf1")
+                && spf_mechanisms(required)
+                    .iter()
+                    .all(|mechanism| found.split_whitespace().any(|term| term == *mechanism))
+        }
+        RecordPurpose::Mx | RecordPurpose::Tracking => {
+            normalize_host(required) == normalize_host(found)
+        }
+        RecordPurpose::Dkim | RecordPurpose::Other => {
+            without_whitespace(required) == without_whitespace(found)
+        }
+    }
+}
+
+fn check_record(
+    domain: &Domain,
+    record: &DnsRecord,
+    resolver: &dyn DnsResolver,
+    cache: &mut HashMap<(RecordType, String), Result<Vec<String>, String>>,
+) -> RecordDiff {
+    let purpose = RecordPurpose::of(record);
+    // Mailgun leaves out the name of records on the domain itself, e.g. MX.
+    let name = normalize_host(record.name.as_deref().unwrap_or(&domain.name));
+    let status = match RecordType::from_record(record) {
+        None => RecordStatus::UnsupportedType(record.record_type.clone()),
+        Some(record_type) => {
+            let found = cache.entry((record_type, name.clone())).or_insert_with(|| {
+                resolver
+                    .lookup(record_type, &name)
+                    .map_err(|err| err.to_string())
+            });
+            match found {
+                Err(err) => RecordStatus::LookupFailed(err.clone()),
+                Ok(values) => {
+                    // SPF is one of possibly many TXT records at the name.
+                    let candidates: Vec<String> = match purpose {
+                        RecordPurpose::Spf => values
+                            .iter()
+                            .filter(|value| value.trim_matches('"').starts_with("v=spf1"))
+                            .cloned()
+                            .collect(),
+                        _ => values.clone(),
+                    };
+                    if candidates
+                        .iter()
+                        .any(|value| matches(purpose, &record.value, value))
+                    {
+                        RecordStatus::Ok
+                    } else if candidates.is_empty() || purpose == RecordPurpose::Mx {
+                        // Other MX hosts can sit next to Mailgun's.
+                        RecordStatus::Missing
+                    } else {
+                        RecordStatus::Mismatched { found: candidates }
+                    }
+                }
+            }
+        }
+    };
+    RecordDiff {
+        purpose,
+        name,
+        record: record.clone(),
+        status,
+    }
+}
+
+/// Compares the sending and receiving records `domain` requires with what
+/// `resolver` finds. Use a domain from [`crate::Mailgun::get_domain`], since
+/// listed domains carry no records.
+pub fn check_records(domain: &Domain, resolver: &dyn DnsResolver) -> DnsReport {
+    let mut cache = HashMap::new();
+    let records = domain
+        .sending_dns_records
+        .iter()
+        .chain(&domain.receiving_dns_records)
+        .map(|record| check_record(domain, record, resolver, &mut cache))
+        .collect();
+    DnsReport { records }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn domain() -> Domain {
+        serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "name": "mg.example.com",
+            "state": "unverified",
+            "type": "custom",
+            "spam_action": "disabled",
+            "created_at": "Tue, 23 May 2023 18:14:25 GMT",
+            "sending_dns_records": [
+                {"record_type": "TXT", "name": "mg.example.com", "value": "v=spf1 include:mailgun.org ~all", "valid": "unknown"},
+                {"record_type": "TXT", "name": "mx._domainkey.mg.example.com", "value": "k=rsa; p=MIGfMA0", "valid": "unknown"},
+                {"record_type": "CNAME", "name": "email.mg.example.com", "value": "mailgun.org", "valid": "unknown"}
+            ],
+            "receiving_dns_records": [
+                {"record_type": "MX", "priority": "10", "value": "mxa.mailgun.org", "valid": "unknown"},
+                {"record_type": "MX", "priority": "10", "value": "mxb.mailgun.org", "valid": "unknown"}
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn check_records_should_diff_against_the_zone() {
+        let zone = |record_type: RecordType, name: &str| -> io::Result<Vec<String>> {
+            let values: &[&str] = match (record_type, name) {
+                (RecordType::Txt, "mg.example.com") => &[
+                    "google-site-verification=abc",
+                    "v=spf1 include:_spf.google.com include:mailgun.org -all",
+                ],
+                (RecordType::Txt, "mx._domainkey.mg.example.com") => &["k=rsa; p=OLDKEY"],
+                (RecordType::Mx, "mg.example.com") => &["MXA.mailgun.org."],
+                _ => &[],
+            };
+            Ok(values.iter().map(|value| value.to_string()).collect())
+        };
+
+        let report = check_records(&domain(), &zone);
+        let statuses: Vec<(RecordPurpose, &RecordStatus)> = report
+            .records
+            .iter()
+            .map(|diff| (diff.purpose, &diff.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (RecordPurpose::Spf, &RecordStatus::Ok),
+                (
+                    RecordPurpose::Dkim,
+                    &RecordStatus::Mismatched {
+                        found: vec!["k=rsa; p=OLDKEY".to_string()]
+                    }
+                ),
+                (RecordPurpose::Tracking, &RecordStatus::Missing),
+                (RecordPurpose::Mx, &RecordStatus::Ok),
+                (RecordPurpose::Mx, &RecordStatus::Missing),
+            ]
+        );
+        assert!(!report.is_ok());
+        assert_eq!(report.problems().count(), 3);
+    }
+
+    #[test]
+    fn spf_mechanisms_should_only_skip_the_all_policy() {
+        assert_eq!(
+            spf_mechanisms("v=spf1 include:mailgun.org include:spf.firewall -all"),
+            vec!["include:mailgun.org", "include:spf.firewall"]
+        );
+        assert_eq!(
+            spf_mechanisms("v=spf1 a:mail ?all +all all"),
+            vec!["a:mail"]
+        );
+    }
+
+    #[test]
+    fn unsupported_record_types_should_be_reported_as_such() {
+        let mut domain = domain();
+        domain.sending_dns_records[0].record_type = "SRV".to_string();
+        let zone = |_: RecordType, _: &str| -> io::Result<Vec<String>> { Ok(Vec::new()) };
+
+        let report = check_records(&domain, &zone);
+        assert_eq!(
+            report.records[0].status,
+            RecordStatus::UnsupportedType("SRV".to_string())
+        );
+    }
+}
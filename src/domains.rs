@@ -0,0 +1,418 @@
+use crate::{build_url, get_base_url, versioned_url, Mailgun, MailgunRegion, SendResult};
+use reqwest::Method;
+use serde::Deserialize;
+use typed_builder::TypedBuilder;
+
+const API_VERSION: &str = "v4";
+const DOMAINS_ENDPOINT: &str = "domains";
+const VERIFY_ENDPOINT: &str = "verify";
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum DomainState {
+    Active,
+    Unverified,
+    Disabled,
+    #[serde(other)]
+    Unknown,
+}
+
+/// What Mailgun does with inbound spam.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(from = "String")]
+pub enum SpamAction {
+    Disabled,
+    Block,
+    Tag,
+    /// An action this crate does not know yet, sent back to Mailgun as is.
+    Other(String),
+}
+
+impl SpamAction {
+    fn as_str(&self) -> &str {
+        match self {
+            SpamAction::Disabled => "disabled",
+            SpamAction::Block => "block",
+            SpamAction::Tag => "tag",
+            SpamAction::Other(action) => action,
+        }
+    }
+}
+
+impl From<String> for SpamAction {
+    fn from(action: String) -> Self {
+        match action.as_str() {
+            "disabled" => SpamAction::Disabled,
+            "block" => SpamAction::Block,
+            "tag" => SpamAction::Tag,
+            _ => SpamAction::Other(action),
+        }
+    }
+}
+
+/// Scheme of tracking links.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(from = "String")]
+pub enum WebScheme {
+    Http,
+    Https,
+    /// A scheme this crate does not know yet, sent back to Mailgun as is.
+    Other(String),
+}
+
+impl WebScheme {
+    fn as_str(&self) -> &str {
+        match self {
+            WebScheme::Http => "http",
+            WebScheme::Https => "https",
+            WebScheme::Other(scheme) => scheme,
+        }
+    }
+}
+
+impl From<String> for WebScheme {
+    fn from(scheme: String) -> Self {
+        match scheme.as_str() {
+            "http" => WebScheme::Http,
+            "https" => WebScheme::Https,
+            _ => WebScheme::Other(scheme),
+        }
+    }
+}
+
+/// Whether Mailgun last saw a DNS record as configured.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordValidity {
+    Valid,
+    Invalid,
+    #[serde(other)]
+    Unknown,
+}
+
+/// A DNS record Mailgun requires for a domain.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct DnsRecord {
+    /// `TXT`, `MX` or `CNAME`.
+    pub record_type: String,
+    /// Host name; Mailgun omits it for receiving records.
+    pub name: Option<String>,
+    pub value: String,
+    pub priority: Option<String>,
+    pub valid: RecordValidity,
+    /// Values Mailgun found at its last check.
+    #[serde(default)]
+    pub cached: Vec<String>,
+    /// Whether the record is in use, e.g. for the domain's active DKIM key.
+    #[serde(default)]
+    pub is_active: bool,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Domain {
+    pub id: String,
+    pub name: String,
+    pub state: DomainState,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub spam_action: SpamAction,
+    #[serde(default)]
+    pub wildcard: bool,
+    pub web_scheme: Option<WebScheme>,
+    pub web_prefix: Option<String>,
+    #[serde(default)]
+    pub smtp_login: String,
+    #[serde(default)]
+    pub is_disabled: bool,
+    pub created_at: String,
+    /// Empty in [`Mailgun::list_domains`] results.
+    #[serde(default)]
+    pub sending_dns_records: Vec<DnsRecord>,
+    /// Empty in [`Mailgun::list_domains`] results.
+    #[serde(default)]
+    pub receiving_dns_records: Vec<DnsRecord>,
+}
+
+#[derive(TypedBuilder, Debug, PartialEq, Eq, Clone)]
+pub struct NewDomain {
+    #[builder(setter(into))]
+    pub name: String,
+    /// Password of the domain's default SMTP login.
+    #[builder(default, setter(strip_option, into))]
+    pub smtp_password: Option<String>,
+    #[builder(default, setter(strip_option))]
+    pub spam_action: Option<SpamAction>,
+    /// Accept mail for subdomains too.
+    #[builder(default, setter(strip_option))]
+    pub wildcard: Option<bool>,
+    #[builder(default, setter(strip_option))]
+    pub web_scheme: Option<WebScheme>,
+    /// 1024 or 2048.
+    #[builder(default, setter(strip_option))]
+    pub dkim_key_size: Option<u32>,
+}
+
+impl NewDomain {
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![("name", self.name.clone())];
+        if let Some(ref password) = self.smtp_password {
+            params.push(("smtp_password", password.clone()));
+        }
+        if let Some(ref spam_action) = self.spam_action {
+            params.push(("spam_action", spam_action.as_str().to_string()));
+        }
+        if let Some(wildcard) = self.wildcard {
+            params.push(("wildcard", wildcard.to_string()));
+        }
+        if let Some(ref web_scheme) = self.web_scheme {
+            params.push(("web_scheme", web_scheme.as_str().to_string()));
+        }
+        if let Some(size) = self.dkim_key_size {
+            params.push(("dkim_key_size", size.to_string()));
+        }
+        params
+    }
+}
+
+/// Settings to change on a domain; unset fields are left as they are.
+#[derive(TypedBuilder, Debug, Default, PartialEq, Eq, Clone)]
+pub struct DomainUpdate {
+    #[builder(default, setter(strip_option))]
+    pub spam_action: Option<SpamAction>,
+    #[builder(default, setter(strip_option))]
+    pub wildcard: Option<bool>,
+    #[builder(default, setter(strip_option))]
+    pub web_scheme: Option<WebScheme>,
+    #[builder(default, setter(strip_option, into))]
+    pub web_prefix: Option<String>,
+}
+
+impl DomainUpdate {
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = Vec::new();
+        if let Some(ref spam_action) = self.spam_action {
+            params.push(("spam_action", spam_action.as_str().to_string()));
+        }
+        if let Some(wildcard) = self.wildcard {
+            params.push(("wildcard", wildcard.to_string()));
+        }
+        if let Some(ref web_scheme) = self.web_scheme {
+            params.push(("web_scheme", web_scheme.as_str().to_string()));
+        }
+        if let Some(ref prefix) = self.web_prefix {
+            params.push(("web_prefix", prefix.clone()));
+        }
+        params
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct DomainList {
+    pub total_count: usize,
+    pub items: Vec<Domain>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DomainResponse {
+    pub message: String,
+    pub domain: Domain,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct DeleteDomainResponse {
+    pub message: String,
+}
+
+/// Mailgun returns a domain's DNS records next to it rather than inside it.
+#[derive(Deserialize)]
+struct DomainEnvelope {
+    #[serde(default)]
+    message: String,
+    domain: Domain,
+    #[serde(default)]
+    sending_dns_records: Vec<DnsRecord>,
+    #[serde(default)]
+    receiving_dns_records: Vec<DnsRecord>,
+}
+
+impl From<DomainEnvelope> for DomainResponse {
+    fn from(envelope: DomainEnvelope) -> Self {
+        let mut domain = envelope.domain;
+        domain.sending_dns_records = envelope.sending_dns_records;
+        domain.receiving_dns_records = envelope.receiving_dns_records;
+        DomainResponse {
+            message: envelope.message,
+            domain,
+        }
+    }
+}
+
+fn domains_url(region: &MailgunRegion, segments: &[&str]) -> reqwest::Url {
+    let mut path = vec![DOMAINS_ENDPOINT];
+    path.extend(segments);
+    versioned_url(region, API_VERSION, &path)
+}
+
+impl Mailgun {
+    /// Lists the account's domains, skipping the first `skip`. These calls
+    /// act on the account, not on `self.domain`.
+    pub fn list_domains(&self, skip: usize, limit: usize) -> SendResult<DomainList> {
+        Mailgun::blocking_json(
+            self.blocking_request(Method::GET, domains_url(&self.region, &[]))?
+                .query(&[("skip", skip), ("limit", limit)]),
+        )
+    }
+
+    /// Fetches a domain with the DNS records it needs.
+    pub fn get_domain(&self, name: &str) -> SendResult<Domain> {
+        let envelope: DomainEnvelope = Mailgun::blocking_json(
+            self.blocking_request(Method::GET, domains_url(&self.region, &[name]))?,
+        )?;
+        Ok(DomainResponse::from(envelope).domain)
+    }
+
+    pub fn create_domain(&self, domain: &NewDomain) -> SendResult<DomainResponse> {
+        let envelope: DomainEnvelope = Mailgun::blocking_json(
+            self.blocking_request(Method::POST, domains_url(&self.region, &[]))?
+                .form(&domain.params()),
+        )?;
+        Ok(envelope.into())
+    }
+
+    pub fn update_domain(&self, name: &str, update: &DomainUpdate) -> SendResult<DomainResponse> {
+        let envelope: DomainEnvelope = Mailgun::blocking_json(
+            self.blocking_request(Method::PUT, domains_url(&self.region, &[name]))?
+                .form(&update.params()),
+        )?;
+        Ok(envelope.into())
+    }
+
+    /// Asks Mailgun to check the domain's DNS records again.
+    pub fn verify_domain(&self, name: &str) -> SendResult<DomainResponse> {
+        let envelope: DomainEnvelope = Mailgun::blocking_json(self.blocking_request(
+            Method::PUT,
+            domains_url(&self.region, &[name, VERIFY_ENDPOINT]),
+        )?)?;
+        Ok(envelope.into())
+    }
+
+    pub fn delete_domain(&self, name: &str) -> SendResult<DeleteDomainResponse> {
+        // Deleting is only available in the v3 API.
+        let url = build_url(get_base_url(&self.region), &[DOMAINS_ENDPOINT, name]);
+        Mailgun::blocking_json(self.blocking_request(Method::DELETE, url)?)
+    }
+
+    pub async fn async_list_domains(&self, skip: usize, limit: usize) -> SendResult<DomainList> {
+        Mailgun::async_json(
+            self.async_request(Method::GET, domains_url(&self.region, &[]))?
+                .query(&[("skip", skip), ("limit", limit)]),
+        )
+        .await
+    }
+
+    pub async fn async_get_domain(&self, name: &str) -> SendResult<Domain> {
+        let envelope: DomainEnvelope = Mailgun::async_json(
+            self.async_request(Method::GET, domains_url(&self.region, &[name]))?,
+        )
+        .await?;
+        Ok(DomainResponse::from(envelope).domain)
+    }
+
+    pub async fn async_create_domain(&self, domain: &NewDomain) -> SendResult<DomainResponse> {
+        let envelope: DomainEnvelope = Mailgun::async_json(
+            self.async_request(Method::POST, domains_url(&self.region, &[]))?
+                .form(&domain.params()),
+        )
+        .await?;
+        Ok(envelope.into())
+    }
+
+    pub async fn async_update_domain(
+        &self,
+        name: &str,
+        update: &DomainUpdate,
+    ) -> SendResult<DomainResponse> {
+        let envelope: DomainEnvelope = Mailgun::async_json(
+            self.async_request(Method::PUT, domains_url(&self.region, &[name]))?
+                .form(&update.params()),
+        )
+        .await?;
+        Ok(envelope.into())
+    }
+
+    pub async fn async_verify_domain(&self, name: &str) -> SendResult<DomainResponse> {
+        let envelope: DomainEnvelope = Mailgun::async_json(self.async_request(
+            Method::PUT,
+            domains_url(&self.region, &[name, VERIFY_ENDPOINT]),
+        )?)
+        .await?;
+        Ok(envelope.into())
+    }
+
+    pub async fn async_delete_domain(&self, name: &str) -> SendResult<DeleteDomainResponse> {
+        let url = build_url(get_base_url(&self.region), &[DOMAINS_ENDPOINT, name]);
+        Mailgun::async_json(self.async_request(Method::DELETE, url)?).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn domain_response_should_carry_dns_records() {
+        let envelope: DomainEnvelope = serde_json::from_str(
+            r#"{
+                "message": "Domain DNS records have been updated",
+                "domain": {
+                    "id": "646d00a1b32c35364a2ad34f",
+                    "name": "mg.example.com",
+                    "state": "unverified",
+                    "type": "custom",
+                    "spam_action": "disabled",
+                    "wildcard": false,
+                    "web_scheme": "https",
+                    "web_prefix": "email",
+                    "smtp_login": "postmaster@mg.example.com",
+                    "is_disabled": false,
+                    "created_at": "Tue, 23 May 2023 18:14:25 GMT"
+                },
+                "receiving_dns_records": [
+                    {"record_type": "MX", "priority": "10", "value": "mxa.mailgun.org", "valid": "unknown", "cached": []}
+                ],
+                "sending_dns_records": [
+                    {"record_type": "TXT", "name": "mg.example.com", "value": "v=spf1 include:mailgun.org ~all", "valid": "valid", "cached": ["v=spf1 include:mailgun.org ~all"]}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let domain = DomainResponse::from(envelope).domain;
+        assert_eq!(domain.state, DomainState::Unverified);
+        assert_eq!(domain.web_scheme, Some(WebScheme::Https));
+        assert_eq!(domain.sending_dns_records[0].valid, RecordValidity::Valid);
+        assert_eq!(domain.receiving_dns_records[0].name, None);
+
+        let domain: Domain = serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "name": "mg.example.com",
+            "state": "pending",
+            "type": "custom",
+            "spam_action": "quarantine",
+            "web_scheme": "hxxps",
+            "created_at": "Tue, 23 May 2023 18:14:25 GMT"
+        }))
+        .unwrap();
+        assert_eq!(domain.state, DomainState::Unknown);
+        let update = DomainUpdate::builder()
+            .spam_action(domain.spam_action)
+            .web_scheme(domain.web_scheme.unwrap())
+            .build();
+        assert_eq!(
+            update.params(),
+            vec![
+                ("spam_action", "quarantine".to_string()),
+                ("web_scheme", "hxxps".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,324 @@
+use crate::webhooks::WebhookEvent;
+use crate::{build_url, get_base_url, yes_no, Mailgun, Page, SendResult};
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::HashMap;
+use typed_builder::TypedBuilder;
+
+const EVENTS_ENDPOINT: &str = "events";
+
+/// An event as returned by the Events API and posted in webhook bodies as
+/// `event-data`. Fields every event carries live here; the rest depends on
+/// [`EventKind`].
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+pub struct Event {
+    pub id: String,
+    pub timestamp: f64,
+    #[serde(rename = "log-level", default)]
+    pub log_level: Option<String>,
+    #[serde(default)]
+    pub recipient: Option<String>,
+    #[serde(rename = "recipient-domain", default)]
+    pub recipient_domain: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub campaigns: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub envelope: Option<Envelope>,
+    #[serde(default)]
+    pub message: Option<EventMessage>,
+    #[serde(default)]
+    pub storage: Option<Storage>,
+    #[serde(default)]
+    pub flags: HashMap<String, bool>,
+    #[serde(rename = "user-variables", default)]
+    pub user_variables: serde_json::Map<String, serde_json::Value>,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+impl Event {
+    /// The domain webhook this event is posted to, if any.
+    pub fn webhook_event(&self) -> Option<WebhookEvent> {
+        match self.kind {
+            EventKind::Accepted => Some(WebhookEvent::Accepted),
+            EventKind::Delivered { .. } => Some(WebhookEvent::Delivered),
+            EventKind::Failed {
+                severity: Severity::Permanent,
+                ..
+            } => Some(WebhookEvent::PermanentFail),
+            EventKind::Failed {
+                severity: Severity::Temporary,
+                ..
+            } => Some(WebhookEvent::TemporaryFail),
+            EventKind::Opened { .. } => Some(WebhookEvent::Opened),
+            EventKind::Clicked { .. } => Some(WebhookEvent::Clicked),
+            EventKind::Unsubscribed { .. } => Some(WebhookEvent::Unsubscribed),
+            EventKind::Complained => Some(WebhookEvent::Complained),
+            EventKind::Rejected { .. } | EventKind::Stored | EventKind::Other => None,
+        }
+    }
+
+    /// Deserializes the `v:` variables attached at send time into `T`.
+    pub fn user_variables<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_value(serde_json::Value::Object(self.user_variables.clone()))
+    }
+}
+
+/// The event type and the fields specific to it.
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum EventKind {
+    Accepted,
+    Rejected {
+        #[serde(default)]
+        reject: Option<Reject>,
+    },
+    Delivered {
+        #[serde(rename = "delivery-status")]
+        delivery_status: DeliveryStatus,
+    },
+    Failed {
+        severity: Severity,
+        #[serde(default)]
+        reason: FailureReason,
+        #[serde(rename = "delivery-status")]
+        delivery_status: DeliveryStatus,
+    },
+    Opened {
+        #[serde(flatten)]
+        client: ClientContext,
+    },
+    Clicked {
+        url: String,
+        #[serde(flatten)]
+        client: ClientContext,
+    },
+    Unsubscribed {
+        #[serde(flatten)]
+        client: ClientContext,
+    },
+    Complained,
+    Stored,
+    /// Event types this crate does not model yet.
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Reject {
+    #[serde(default)]
+    pub reason: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Permanent,
+    Temporary,
+}
+
+/// Why Mailgun gave up on, or deferred, a delivery.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum FailureReason {
+    /// The receiving server rejected the message.
+    Bounce,
+    /// The recipient is on the bounce list.
+    SuppressBounce,
+    /// The recipient is on the unsubscribe list.
+    SuppressUnsubscribe,
+    /// The recipient is on the complaint list.
+    SuppressComplaint,
+    /// Mailgun stopped retrying after too long.
+    Old,
+    /// Blocked by the receiving ESP.
+    Espblock,
+    #[default]
+    Generic,
+    #[serde(other)]
+    Other,
+}
+
+impl FailureReason {
+    /// Whether Mailgun dropped the message because of an existing
+    /// suppression rather than trying to deliver it.
+    pub fn is_suppression(&self) -> bool {
+        matches!(
+            self,
+            FailureReason::SuppressBounce
+                | FailureReason::SuppressUnsubscribe
+                | FailureReason::SuppressComplaint
+        )
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct DeliveryStatus {
+    pub code: Option<i32>,
+    pub message: String,
+    pub description: String,
+    pub attempt_no: Option<u32>,
+    pub enhanced_code: Option<String>,
+    pub bounce_type: Option<String>,
+    pub mx_host: Option<String>,
+    pub tls: Option<bool>,
+    pub certificate_verified: Option<bool>,
+    pub session_seconds: Option<f64>,
+    pub retry_seconds: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct Envelope {
+    pub sender: Option<String>,
+    pub targets: Option<String>,
+    pub transport: Option<String>,
+    pub sending_ip: Option<String>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(default)]
+pub struct EventMessage {
+    pub headers: HashMap<String, String>,
+    pub attachments: Vec<serde_json::Value>,
+    pub size: Option<u64>,
+}
+
+/// Where Mailgun keeps a copy of the message for a few days.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Storage {
+    pub url: String,
+    pub key: String,
+}
+
+/// Details about the recipient's device for engagement events.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+#[serde(default)]
+pub struct ClientContext {
+    pub ip: Option<String>,
+    pub geolocation: Option<Geolocation>,
+    #[serde(rename = "client-info")]
+    pub client_info: Option<ClientInfo>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+#[serde(default)]
+pub struct Geolocation {
+    pub country: String,
+    pub region: String,
+    pub city: String,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct ClientInfo {
+    pub client_type: String,
+    pub client_os: String,
+    pub client_name: String,
+    pub device_type: String,
+    pub user_agent: String,
+}
+
+/// Filters for [`Mailgun::list_events`]. Times are RFC 2822 dates or unix
+/// timestamps, as the Events API accepts.
+#[derive(TypedBuilder, Serialize, Debug, Default, PartialEq, Eq, Clone)]
+pub struct EventQuery {
+    #[builder(default, setter(strip_option, into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub begin: Option<String>,
+    #[builder(default, setter(strip_option, into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<String>,
+    #[builder(default, setter(strip_option))]
+    #[serde(
+        serialize_with = "serialize_yes_no",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub ascending: Option<bool>,
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[builder(default, setter(strip_option, into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event: Option<String>,
+    #[builder(default, setter(strip_option, into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recipient: Option<String>,
+    #[builder(default, setter(strip_option, into))]
+    #[serde(rename = "message-id", skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<String>,
+}
+
+fn serialize_yes_no<S: Serializer>(value: &Option<bool>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(yes_no(value.unwrap_or_default()))
+}
+
+impl Mailgun {
+    pub fn list_events(&self, query: &EventQuery) -> SendResult<Page<Event>> {
+        let url = build_url(get_base_url(&self.region), &[&self.domain, EVENTS_ENDPOINT]);
+        Mailgun::blocking_json(self.blocking_request(Method::GET, url)?.query(query))
+    }
+
+    pub async fn async_list_events(&self, query: &EventQuery) -> SendResult<Page<Event>> {
+        let url = build_url(get_base_url(&self.region), &[&self.domain, EVENTS_ENDPOINT]);
+        Mailgun::async_json(self.async_request(Method::GET, url)?.query(query)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct OrderVariables {
+        order_id: String,
+    }
+
+    #[test]
+    fn failed_event_should_parse() {
+        let event: Event = serde_json::from_value(serde_json::json!({
+            "id": "G9Bn5sl1TC6nu79C8C0bwg",
+            "timestamp": 1521233195.375624,
+            "log-level": "error",
+            "event": "failed",
+            "severity": "permanent",
+            "reason": "suppress-bounce",
+            "recipient": "alice@example.com",
+            "delivery-status": {
+                "code": 605,
+                "message": "",
+                "description": "Not delivering to previously bounced address",
+                "attempt-no": 1
+            },
+            "storage": {"url": "https://storage.mailgun.net/v3/domains/example.com/messages/key", "key": "key"},
+            "user-variables": {"order_id": "42"},
+            "flags": {"is-routed": false}
+        }))
+        .unwrap();
+
+        match event.kind {
+            EventKind::Failed {
+                severity,
+                ref reason,
+                ref delivery_status,
+            } => {
+                assert_eq!(severity, Severity::Permanent);
+                assert!(reason.is_suppression());
+                assert_eq!(delivery_status.code, Some(605));
+            }
+            ref other => panic!("unexpected kind: {:?}", other),
+        }
+        assert_eq!(
+            event.user_variables::<OrderVariables>().unwrap(),
+            OrderVariables {
+                order_id: "42".to_string()
+            }
+        );
+    }
+}
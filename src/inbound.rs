@@ -0,0 +1,330 @@
+use crate::webhooks::{Signature, Verifier, VerifyError};
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum InboundError {
+    #[error("malformed multipart body: {0}")]
+    Multipart(&'static str),
+
+    #[error("invalid json in inbound message: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// A file Mailgun forwarded along with an inbound message.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct InboundAttachment {
+    /// Form field the file came in, e.g. `attachment-1`.
+    pub field_name: String,
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+    pub data: Vec<u8>,
+}
+
+/// A message a route forwarded to an HTTP endpoint.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct InboundMessage {
+    pub sender: String,
+    pub recipient: String,
+    pub from: String,
+    pub subject: String,
+    pub body_plain: String,
+    pub body_html: Option<String>,
+    pub stripped_text: String,
+    pub stripped_html: Option<String>,
+    pub stripped_signature: Option<String>,
+    /// Headers in their original order; names may repeat.
+    pub message_headers: Vec<(String, String)>,
+    /// Maps `Content-ID`s, without angle brackets, to attachment field names.
+    pub content_id_map: HashMap<String, String>,
+    pub attachments: Vec<InboundAttachment>,
+    /// How many attachments Mailgun says it forwarded.
+    pub attachment_count: Option<usize>,
+    pub timestamp: String,
+    pub token: String,
+    pub signature: String,
+    /// Every text field as posted, including ones without a typed field.
+    pub fields: HashMap<String, String>,
+}
+
+/// Extracts the boundary parameter from a `multipart/form-data` content type.
+pub fn boundary_from_content_type(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if name.trim().eq_ignore_ascii_case("boundary") {
+            Some(value.trim().trim_matches('"'))
+        } else {
+            None
+        }
+    })
+}
+
+/// The number of an `attachment-<n>` field.
+fn attachment_number(field_name: &str) -> Option<usize> {
+    let number = field_name.strip_prefix("attachment-")?;
+    if number.bytes().all(|b| b.is_ascii_digit()) {
+        number.parse().ok()
+    } else {
+        None
+    }
+}
+
+struct FormPart {
+    name: String,
+    filename: Option<String>,
+    content_type: Option<String>,
+    data: Vec<u8>,
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Reads a `key="value"` parameter of a `Content-Disposition` header.
+/// Quoted values may contain `;` and backslash escapes such as `\"`.
+fn disposition_param(disposition: &str, key: &str) -> Option<String> {
+    let mut params = Vec::new();
+    let mut param = String::new();
+    let mut quoted = false;
+    let mut chars = disposition.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => quoted = !quoted,
+            '\\' if quoted => param.extend(chars.next()),
+            ';' if !quoted => params.push(std::mem::take(&mut param)),
+            c => param.push(c),
+        }
+    }
+    params.push(param);
+
+    params.into_iter().skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if name.trim().eq_ignore_ascii_case(key) {
+            Some(value.trim().to_string())
+        } else {
+            None
+        }
+    })
+}
+
+fn parse_multipart(body: &[u8], boundary: &str) -> Result<Vec<FormPart>, InboundError> {
+    let delimiter = format!("--{boundary}");
+    let start =
+        find(body, delimiter.as_bytes()).ok_or(InboundError::Multipart("boundary not found"))?;
+    let mut rest = &body[start + delimiter.len()..];
+    let separator = format!("\r\n--{boundary}");
+    let mut parts = Vec::new();
+
+    // `rest` starts right after a delimiter: `--` closes the body, otherwise
+    // a line break precedes the next part's headers.
+    while !rest.starts_with(b"--") {
+        rest = rest
+            .strip_prefix(b"\r\n")
+            .ok_or(InboundError::Multipart("missing line break after boundary"))?;
+        let end = find(rest, separator.as_bytes())
+            .ok_or(InboundError::Multipart("missing closing boundary"))?;
+        let part = &rest[..end];
+        rest = &rest[end + separator.len()..];
+
+        let header_end =
+            find(part, b"\r\n\r\n").ok_or(InboundError::Multipart("part without headers"))?;
+        let headers = String::from_utf8_lossy(&part[..header_end]);
+        let mut disposition = None;
+        let mut content_type = None;
+        for line in headers.split("\r\n") {
+            if let Some((name, value)) = line.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-disposition") {
+                    disposition = Some(value.trim().to_string());
+                } else if name.trim().eq_ignore_ascii_case("content-type") {
+                    content_type = Some(value.trim().to_string());
+                }
+            }
+        }
+        let disposition =
+            disposition.ok_or(InboundError::Multipart("part without content-disposition"))?;
+        parts.push(FormPart {
+            name: disposition_param(&disposition, "name")
+                .ok_or(InboundError::Multipart("part without a name"))?,
+            filename: disposition_param(&disposition, "filename"),
+            content_type,
+            data: part[header_end + 4..].to_vec(),
+        });
+    }
+
+    Ok(parts)
+}
+
+impl InboundMessage {
+    /// Parses a `multipart/form-data` body. `boundary` comes from the request's
+    /// content type, see [`boundary_from_content_type`].
+    pub fn from_multipart(body: &[u8], boundary: &str) -> Result<Self, InboundError> {
+        let mut fields = HashMap::new();
+        let mut attachments = Vec::new();
+        for part in parse_multipart(body, boundary)? {
+            if part.filename.is_some() || attachment_number(&part.name).is_some() {
+                attachments.push(InboundAttachment {
+                    field_name: part.name,
+                    filename: part.filename,
+                    content_type: part.content_type,
+                    data: part.data,
+                });
+            } else {
+                fields.insert(part.name, String::from_utf8_lossy(&part.data).into_owned());
+            }
+        }
+        InboundMessage::from_fields(fields, attachments)
+    }
+
+    /// Parses a JSON body with the same field names as the form. JSON bodies
+    /// carry no attachment contents.
+    pub fn from_json(body: &[u8]) -> Result<Self, InboundError> {
+        let object: HashMap<String, serde_json::Value> = serde_json::from_slice(body)?;
+        let fields = object
+            .into_iter()
+            .map(|(name, value)| match value {
+                serde_json::Value::String(text) => (name, text),
+                other => (name, other.to_string()),
+            })
+            .collect();
+        InboundMessage::from_fields(fields, Vec::new())
+    }
+
+    fn from_fields(
+        fields: HashMap<String, String>,
+        mut attachments: Vec<InboundAttachment>,
+    ) -> Result<Self, InboundError> {
+        let message_headers: Vec<(String, String)> = match fields.get("message-headers") {
+            Some(headers) => serde_json::from_str(headers)?,
+            None => Vec::new(),
+        };
+        let content_id_map: HashMap<String, String> = match fields.get("content-id-map") {
+            Some(map) => serde_json::from_str::<HashMap<String, String>>(map)?
+                .into_iter()
+                .map(|(cid, field)| {
+                    (
+                        cid.trim_matches(|c| c == '<' || c == '>').to_string(),
+                        field,
+                    )
+                })
+                .collect(),
+            None => HashMap::new(),
+        };
+        attachments.sort_by_key(|attachment| {
+            attachment_number(&attachment.field_name).unwrap_or(usize::MAX)
+        });
+
+        let take = |name: &str| fields.get(name).cloned();
+        Ok(InboundMessage {
+            sender: take("sender").unwrap_or_default(),
+            recipient: take("recipient").unwrap_or_default(),
+            from: take("from").unwrap_or_default(),
+            subject: take("subject").unwrap_or_default(),
+            body_plain: take("body-plain").unwrap_or_default(),
+            body_html: take("body-html"),
+            stripped_text: take("stripped-text").unwrap_or_default(),
+            stripped_html: take("stripped-html"),
+            stripped_signature: take("stripped-signature"),
+            timestamp: take("timestamp").unwrap_or_default(),
+            token: take("token").unwrap_or_default(),
+            signature: take("signature").unwrap_or_default(),
+            message_headers,
+            content_id_map,
+            attachments,
+            attachment_count: take("attachment-count").and_then(|count| count.parse().ok()),
+            fields,
+        })
+    }
+
+    /// First value of the header `name`, compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.message_headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The attachment an inline image refers to, by `Content-ID` with or
+    /// without angle brackets, e.g. from a `cid:` URL in `body_html`.
+    pub fn inline_attachment(&self, content_id: &str) -> Option<&InboundAttachment> {
+        let field = self
+            .content_id_map
+            .get(content_id.trim_matches(|c| c == '<' || c == '>'))?;
+        self.attachments
+            .iter()
+            .find(|attachment| &attachment.field_name == field)
+    }
+
+    pub fn signature(&self) -> Signature {
+        Signature {
+            timestamp: self.timestamp.clone(),
+            token: self.token.clone(),
+            signature: self.signature.clone(),
+        }
+    }
+
+    /// Checks the route signature the same way as webhook signatures.
+    pub fn verify(&self, verifier: &Verifier) -> Result<(), VerifyError> {
+        verifier.verify(&self.signature())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multipart_message_should_resolve_inline_images() {
+        let body = concat!(
+            "--XyZ\r\n",
+            "Content-Disposition: form-data; name=\"recipient\"\r\n\r\n",
+            "support@example.com\r\n",
+            "--XyZ\r\n",
+            "Content-Disposition: form-data; name=\"stripped-text\"\r\n\r\n",
+            "Thanks!\r\n",
+            "--XyZ\r\n",
+            "Content-Disposition: form-data; name=\"message-headers\"\r\n\r\n",
+            "[[\"Subject\", \"Re: ticket 42\"], [\"In-Reply-To\", \"<42@example.com>\"]]\r\n",
+            "--XyZ\r\n",
+            "Content-Disposition: form-data; name=\"content-id-map\"\r\n\r\n",
+            "{\"<ii_1>\": \"attachment-1\"}\r\n",
+            "--XyZ\r\n",
+            "Content-Disposition: form-data; name=\"attachment-count\"\r\n\r\n",
+            "1\r\n",
+            "--XyZ\r\n",
+            "Content-Disposition: form-data; name=\"attachment-1\"; filename=\"logo.png\"\r\n",
+            "Content-Type: image/png\r\n\r\n",
+            "\x01\x02\r\n\x03\r\n",
+            "--XyZ--\r\n",
+        );
+        assert_eq!(
+            boundary_from_content_type("multipart/form-data; boundary=\"XyZ\""),
+            Some("XyZ")
+        );
+
+        let message = InboundMessage::from_multipart(body.as_bytes(), "XyZ").unwrap();
+        assert_eq!(message.recipient, "support@example.com");
+        assert_eq!(message.stripped_text, "Thanks!");
+        assert_eq!(message.header("in-reply-to"), Some("<42@example.com>"));
+        let logo = message.inline_attachment("ii_1").unwrap();
+        assert_eq!(logo.filename.as_deref(), Some("logo.png"));
+        assert_eq!(logo.data, b"\x01\x02\r\n\x03");
+        assert_eq!(message.attachment_count, Some(1));
+        assert_eq!(message.fields["attachment-count"], "1");
+        assert_eq!(message.attachments.len(), 1);
+    }
+
+    #[test]
+    fn disposition_params_should_honour_quoted_strings() {
+        let disposition = r#"form-data; name="attachment-1"; filename="a;b \"final\".pdf""#;
+        assert_eq!(
+            disposition_param(disposition, "filename").as_deref(),
+            Some(r#"a;b "final".pdf"#)
+        );
+        assert_eq!(
+            disposition_param(disposition, "name").as_deref(),
+            Some("attachment-1")
+        );
+    }
+}
@@ -0,0 +1,343 @@
+use crate::{build_url, get_base_url, versioned_url, Mailgun, MailgunRegion, SendResult};
+use reqwest::Method;
+use serde::Deserialize;
+use typed_builder::TypedBuilder;
+
+const IPS_ENDPOINT: &str = "ips";
+const DOMAINS_ENDPOINT: &str = "domains";
+const IP_POOLS_ENDPOINT: &str = "ip_pools";
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct IpList {
+    pub total_count: usize,
+    pub items: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct IpDetails {
+    pub ip: String,
+    pub dedicated: bool,
+    /// Reverse DNS name of the IP.
+    #[serde(default)]
+    pub rdns: String,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct IpResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct IpPool {
+    pub pool_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub ips: Vec<String>,
+    /// Whether any domain sends through the pool.
+    #[serde(default)]
+    pub is_linked: bool,
+}
+
+#[derive(TypedBuilder, Debug, PartialEq, Eq, Clone)]
+pub struct NewIpPool {
+    #[builder(setter(into))]
+    pub name: String,
+    #[builder(default, setter(into))]
+    pub description: String,
+    #[builder(default)]
+    pub ips: Vec<String>,
+}
+
+impl NewIpPool {
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![
+            ("name", self.name.clone()),
+            ("description", self.description.clone()),
+        ];
+        for ip in &self.ips {
+            params.push(("ip", ip.clone()));
+        }
+        params
+    }
+}
+
+/// Changes to an IP pool; unset and empty fields are left as they are.
+#[derive(TypedBuilder, Debug, Default, PartialEq, Eq, Clone)]
+pub struct IpPoolUpdate {
+    #[builder(default, setter(strip_option, into))]
+    pub name: Option<String>,
+    #[builder(default, setter(strip_option, into))]
+    pub description: Option<String>,
+    #[builder(default)]
+    pub add_ips: Vec<String>,
+    #[builder(default)]
+    pub remove_ips: Vec<String>,
+    /// Domains to send through the pool.
+    #[builder(default)]
+    pub link_domains: Vec<String>,
+    #[builder(default)]
+    pub unlink_domains: Vec<String>,
+}
+
+impl IpPoolUpdate {
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = Vec::new();
+        if let Some(ref name) = self.name {
+            params.push(("name", name.clone()));
+        }
+        if let Some(ref description) = self.description {
+            params.push(("description", description.clone()));
+        }
+        let repeated = [
+            ("add_ip", &self.add_ips),
+            ("remove_ip", &self.remove_ips),
+            ("link_domain", &self.link_domains),
+            ("unlink_domain", &self.unlink_domains),
+        ];
+        for (key, values) in repeated {
+            params.extend(values.iter().map(|value| (key, value.clone())));
+        }
+        params
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct IpPoolResponse {
+    pub message: String,
+    #[serde(default)]
+    pub pool_id: Option<String>,
+}
+
+/// Where a deleted pool's linked domains send from instead.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PoolReplacement {
+    Ip(String),
+    Pool(String),
+}
+
+impl PoolReplacement {
+    fn query(replacement: Option<&PoolReplacement>) -> Vec<(&'static str, &str)> {
+        match replacement {
+            Some(PoolReplacement::Ip(ip)) => vec![("ip", ip)],
+            Some(PoolReplacement::Pool(pool_id)) => vec![("pool_id", pool_id)],
+            None => Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct IpPoolList {
+    ip_pools: Vec<IpPool>,
+}
+
+fn ips_url(region: &MailgunRegion, ip: Option<&str>) -> reqwest::Url {
+    let mut segments = vec![IPS_ENDPOINT];
+    segments.extend(ip);
+    build_url(get_base_url(region), &segments)
+}
+
+fn domain_ips_url(region: &MailgunRegion, domain: &str, ip: Option<&str>) -> reqwest::Url {
+    let mut segments = vec![DOMAINS_ENDPOINT, domain, IPS_ENDPOINT];
+    segments.extend(ip);
+    build_url(get_base_url(region), &segments)
+}
+
+fn ip_pools_url(region: &MailgunRegion, pool_id: Option<&str>) -> reqwest::Url {
+    let mut segments = vec![IP_POOLS_ENDPOINT];
+    segments.extend(pool_id);
+    versioned_url(region, "v1", &segments)
+}
+
+impl Mailgun {
+    /// Lists the account's IPs, only dedicated ones if `dedicated_only`.
+    pub fn list_ips(&self, dedicated_only: bool) -> SendResult<IpList> {
+        Mailgun::blocking_json(
+            self.blocking_request(Method::GET, ips_url(&self.region, None))?
+                .query(&[("dedicated", dedicated_only)]),
+        )
+    }
+
+    pub fn get_ip(&self, ip: &str) -> SendResult<IpDetails> {
+        Mailgun::blocking_json(self.blocking_request(Method::GET, ips_url(&self.region, Some(ip)))?)
+    }
+
+    pub fn list_domain_ips(&self, domain: &str) -> SendResult<IpList> {
+        Mailgun::blocking_json(
+            self.blocking_request(Method::GET, domain_ips_url(&self.region, domain, None))?,
+        )
+    }
+
+    pub fn assign_ip(&self, domain: &str, ip: &str) -> SendResult<IpResponse> {
+        Mailgun::blocking_json(
+            self.blocking_request(Method::POST, domain_ips_url(&self.region, domain, None))?
+                .form(&[("ip", ip)]),
+        )
+    }
+
+    pub fn unassign_ip(&self, domain: &str, ip: &str) -> SendResult<IpResponse> {
+        Mailgun::blocking_json(self.blocking_request(
+            Method::DELETE,
+            domain_ips_url(&self.region, domain, Some(ip)),
+        )?)
+    }
+
+    pub fn list_ip_pools(&self) -> SendResult<Vec<IpPool>> {
+        let list: IpPoolList = Mailgun::blocking_json(
+            self.blocking_request(Method::GET, ip_pools_url(&self.region, None))?,
+        )?;
+        Ok(list.ip_pools)
+    }
+
+    pub fn create_ip_pool(&self, pool: &NewIpPool) -> SendResult<IpPoolResponse> {
+        Mailgun::blocking_json(
+            self.blocking_request(Method::POST, ip_pools_url(&self.region, None))?
+                .form(&pool.params()),
+        )
+    }
+
+    pub fn update_ip_pool(
+        &self,
+        pool_id: &str,
+        update: &IpPoolUpdate,
+    ) -> SendResult<IpPoolResponse> {
+        Mailgun::blocking_json(
+            self.blocking_request(Method::PATCH, ip_pools_url(&self.region, Some(pool_id)))?
+                .form(&update.params()),
+        )
+    }
+
+    /// Makes `domains` send through the pool.
+    pub fn link_ip_pool_domains(
+        &self,
+        pool_id: &str,
+        domains: &[&str],
+    ) -> SendResult<IpPoolResponse> {
+        let update = IpPoolUpdate::builder()
+            .link_domains(domains.iter().map(|domain| domain.to_string()).collect())
+            .build();
+        self.update_ip_pool(pool_id, &update)
+    }
+
+    /// Deletes a pool. Domains linked to it move to `replacement`, which
+    /// Mailgun requires while any are.
+    pub fn delete_ip_pool(
+        &self,
+        pool_id: &str,
+        replacement: Option<&PoolReplacement>,
+    ) -> SendResult<IpPoolResponse> {
+        Mailgun::blocking_json(
+            self.blocking_request(Method::DELETE, ip_pools_url(&self.region, Some(pool_id)))?
+                .query(&PoolReplacement::query(replacement)),
+        )
+    }
+
+    pub async fn async_list_ips(&self, dedicated_only: bool) -> SendResult<IpList> {
+        Mailgun::async_json(
+            self.async_request(Method::GET, ips_url(&self.region, None))?
+                .query(&[("dedicated", dedicated_only)]),
+        )
+        .await
+    }
+
+    pub async fn async_get_ip(&self, ip: &str) -> SendResult<IpDetails> {
+        Mailgun::async_json(self.async_request(Method::GET, ips_url(&self.region, Some(ip)))?).await
+    }
+
+    pub async fn async_list_domain_ips(&self, domain: &str) -> SendResult<IpList> {
+        Mailgun::async_json(
+            self.async_request(Method::GET, domain_ips_url(&self.region, domain, None))?,
+        )
+        .await
+    }
+
+    pub async fn async_assign_ip(&self, domain: &str, ip: &str) -> SendResult<IpResponse> {
+        Mailgun::async_json(
+            self.async_request(Method::POST, domain_ips_url(&self.region, domain, None))?
+                .form(&[("ip", ip)]),
+        )
+        .await
+    }
+
+    pub async fn async_unassign_ip(&self, domain: &str, ip: &str) -> SendResult<IpResponse> {
+        Mailgun::async_json(self.async_request(
+            Method::DELETE,
+            domain_ips_url(&self.region, domain, Some(ip)),
+        )?)
+        .await
+    }
+
+    pub async fn async_list_ip_pools(&self) -> SendResult<Vec<IpPool>> {
+        let list: IpPoolList =
+            Mailgun::async_json(self.async_request(Method::GET, ip_pools_url(&self.region, None))?)
+                .await?;
+        Ok(list.ip_pools)
+    }
+
+    pub async fn async_create_ip_pool(&self, pool: &NewIpPool) -> SendResult<IpPoolResponse> {
+        Mailgun::async_json(
+            self.async_request(Method::POST, ip_pools_url(&self.region, None))?
+                .form(&pool.params()),
+        )
+        .await
+    }
+
+    pub async fn async_update_ip_pool(
+        &self,
+        pool_id: &str,
+        update: &IpPoolUpdate,
+    ) -> SendResult<IpPoolResponse> {
+        Mailgun::async_json(
+            self.async_request(Method::PATCH, ip_pools_url(&self.region, Some(pool_id)))?
+                .form(&update.params()),
+        )
+        .await
+    }
+
+    pub async fn async_link_ip_pool_domains(
+        &self,
+        pool_id: &str,
+        domains: &[&str],
+    ) -> SendResult<IpPoolResponse> {
+        let update = IpPoolUpdate::builder()
+            .link_domains(domains.iter().map(|domain| domain.to_string()).collect())
+            .build();
+        self.async_update_ip_pool(pool_id, &update).await
+    }
+
+    pub async fn async_delete_ip_pool(
+        &self,
+        pool_id: &str,
+        replacement: Option<&PoolReplacement>,
+    ) -> SendResult<IpPoolResponse> {
+        Mailgun::async_json(
+            self.async_request(Method::DELETE, ip_pools_url(&self.region, Some(pool_id)))?
+                .query(&PoolReplacement::query(replacement)),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pool_update_should_repeat_list_params() {
+        let update = IpPoolUpdate::builder()
+            .description("marketing")
+            .add_ips(vec!["10.0.0.1".to_string(), "10.0.0.2".to_string()])
+            .link_domains(vec!["news.example.com".to_string()])
+            .build();
+        assert_eq!(
+            update.params(),
+            vec![
+                ("description", "marketing".to_string()),
+                ("add_ip", "10.0.0.1".to_string()),
+                ("add_ip", "10.0.0.2".to_string()),
+                ("link_domain", "news.example.com".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,391 @@
+use crate::secret::Secret;
+use crate::{versioned_url, Mailgun, MailgunRegion, SendResult};
+use reqwest::Method;
+use serde::Deserialize;
+use std::time::Duration;
+use typed_builder::TypedBuilder;
+
+const KEYS_ENDPOINT: &str = "keys";
+
+/// What a key is allowed to do.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(from = "String")]
+pub enum KeyRole {
+    Admin,
+    Developer,
+    Support,
+    Basic,
+    /// Only sending messages, the role for domain sending keys.
+    Sending,
+    /// A role this crate does not know yet, sent back to Mailgun as is.
+    Other(String),
+}
+
+impl KeyRole {
+    fn as_str(&self) -> &str {
+        match self {
+            KeyRole::Admin => "admin",
+            KeyRole::Developer => "developer",
+            KeyRole::Support => "support",
+            KeyRole::Basic => "basic",
+            KeyRole::Sending => "sending",
+            KeyRole::Other(role) => role,
+        }
+    }
+}
+
+impl From<String> for KeyRole {
+    fn from(role: String) -> Self {
+        match role.as_str() {
+            "admin" => KeyRole::Admin,
+            "developer" => KeyRole::Developer,
+            "support" => KeyRole::Support,
+            "basic" => KeyRole::Basic,
+            "sending" => KeyRole::Sending,
+            _ => KeyRole::Other(role),
+        }
+    }
+}
+
+/// What a key is scoped to.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(from = "String")]
+pub enum KeyKind {
+    /// Limited to one domain.
+    Domain,
+    /// Belongs to a user of the account.
+    User,
+    /// Short-lived key of a web session.
+    Web,
+    /// A kind this crate does not know yet, sent back to Mailgun as is.
+    Other(String),
+}
+
+impl KeyKind {
+    fn as_str(&self) -> &str {
+        match self {
+            KeyKind::Domain => "domain",
+            KeyKind::User => "user",
+            KeyKind::Web => "web",
+            KeyKind::Other(kind) => kind,
+        }
+    }
+}
+
+impl From<String> for KeyKind {
+    fn from(kind: String) -> Self {
+        match kind.as_str() {
+            "domain" => KeyKind::Domain,
+            "user" => KeyKind::User,
+            "web" => KeyKind::Web,
+            _ => KeyKind::Other(kind),
+        }
+    }
+}
+
+/// An API key as listed. The secret is only returned when it is created.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ApiKey {
+    pub id: String,
+    #[serde(default)]
+    pub description: String,
+    pub kind: KeyKind,
+    pub role: KeyRole,
+    pub domain_name: Option<String>,
+    pub created_at: String,
+    pub expires_at: Option<String>,
+    #[serde(default)]
+    pub is_disabled: bool,
+}
+
+#[derive(TypedBuilder, Debug, PartialEq, Eq, Clone)]
+pub struct NewApiKey {
+    pub role: KeyRole,
+    #[builder(default, setter(strip_option))]
+    pub kind: Option<KeyKind>,
+    /// Domain a `KeyKind::Domain` key is limited to.
+    #[builder(default, setter(strip_option, into))]
+    pub domain_name: Option<String>,
+    #[builder(default, setter(strip_option, into))]
+    pub description: Option<String>,
+    /// How long until the key stops working; it never expires when unset.
+    #[builder(default, setter(strip_option))]
+    pub expiration: Option<Duration>,
+}
+
+impl NewApiKey {
+    /// A sending-only key limited to `domain`, e.g. for one service.
+    pub fn sending(domain: &str, description: &str, expiration: Option<Duration>) -> Self {
+        NewApiKey {
+            role: KeyRole::Sending,
+            kind: Some(KeyKind::Domain),
+            domain_name: Some(domain.to_string()),
+            description: Some(description.to_string()),
+            expiration,
+        }
+    }
+
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![("role", self.role.as_str().to_string())];
+        if let Some(ref kind) = self.kind {
+            params.push(("kind", kind.as_str().to_string()));
+        }
+        if let Some(ref domain) = self.domain_name {
+            params.push(("domain_name", domain.clone()));
+        }
+        if let Some(ref description) = self.description {
+            params.push(("description", description.clone()));
+        }
+        if let Some(expiration) = self.expiration {
+            params.push(("expiration", expiration.as_secs().to_string()));
+        }
+        params
+    }
+}
+
+/// A key that was just created, with its secret.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CreatedApiKey {
+    pub key: ApiKey,
+    pub secret: Secret,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ApiKeyResponse {
+    pub message: String,
+}
+
+/// Result of [`Mailgun::provision_sending_key`]. Deploy `key`, then call
+/// [`Mailgun::retire_superseded_keys`] to delete the keys it replaces.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct KeyRotation {
+    pub key: CreatedApiKey,
+    /// Earlier keys of the same domain and description.
+    pub superseded: Vec<ApiKey>,
+}
+
+#[derive(Deserialize)]
+struct KeyList {
+    items: Vec<ApiKey>,
+}
+
+#[derive(Deserialize)]
+struct CreatedKeyEnvelope {
+    key: CreatedKeyFields,
+}
+
+#[derive(Deserialize)]
+struct CreatedKeyFields {
+    #[serde(flatten)]
+    key: ApiKey,
+    secret: String,
+}
+
+impl From<CreatedKeyEnvelope> for CreatedApiKey {
+    fn from(envelope: CreatedKeyEnvelope) -> Self {
+        CreatedApiKey {
+            key: envelope.key.key,
+            secret: Secret::from(envelope.key.secret),
+        }
+    }
+}
+
+fn keys_url(region: &MailgunRegion, id: Option<&str>) -> reqwest::Url {
+    let mut segments = vec![KEYS_ENDPOINT];
+    segments.extend(id);
+    versioned_url(region, "v1", &segments)
+}
+
+fn list_query<'a>(
+    domain: Option<&'a str>,
+    kind: Option<&'a KeyKind>,
+) -> Vec<(&'static str, &'a str)> {
+    let mut query = Vec::new();
+    if let Some(domain) = domain {
+        query.push(("domain_name", domain));
+    }
+    if let Some(kind) = kind {
+        query.push(("kind", kind.as_str()));
+    }
+    query
+}
+
+fn superseded(keys: Vec<ApiKey>, key: &CreatedApiKey) -> Vec<ApiKey> {
+    keys.into_iter()
+        .filter(|existing| {
+            existing.id != key.key.id
+                && existing.role == KeyRole::Sending
+                && existing.description == key.key.description
+        })
+        .collect()
+}
+
+impl Mailgun {
+    /// Lists the account's keys, optionally only those of `domain` or `kind`.
+    pub fn list_api_keys(
+        &self,
+        domain: Option<&str>,
+        kind: Option<KeyKind>,
+    ) -> SendResult<Vec<ApiKey>> {
+        let list: KeyList = Mailgun::blocking_json(
+            self.blocking_request(Method::GET, keys_url(&self.region, None))?
+                .query(&list_query(domain, kind.as_ref())),
+        )?;
+        Ok(list.items)
+    }
+
+    pub fn create_api_key(&self, key: &NewApiKey) -> SendResult<CreatedApiKey> {
+        let envelope: CreatedKeyEnvelope = Mailgun::blocking_json(
+            self.blocking_request(Method::POST, keys_url(&self.region, None))?
+                .form(&key.params()),
+        )?;
+        Ok(envelope.into())
+    }
+
+    pub fn delete_api_key(&self, id: &str) -> SendResult<ApiKeyResponse> {
+        Mailgun::blocking_json(
+            self.blocking_request(Method::DELETE, keys_url(&self.region, Some(id)))?,
+        )
+    }
+
+    /// Creates a sending key for `domain` identified by `description`, e.g.
+    /// a service name, and lists the earlier keys it supersedes. Nothing is
+    /// deleted yet, so the service keeps working until it has the new key.
+    pub fn provision_sending_key(
+        &self,
+        domain: &str,
+        description: &str,
+        expiration: Option<Duration>,
+    ) -> SendResult<KeyRotation> {
+        let existing = self.list_api_keys(Some(domain), Some(KeyKind::Domain))?;
+        let key = self.create_api_key(&NewApiKey::sending(domain, description, expiration))?;
+        Ok(KeyRotation {
+            superseded: superseded(existing, &key),
+            key,
+        })
+    }
+
+    /// Deletes the keys a rotation superseded.
+    pub fn retire_superseded_keys(&self, rotation: &KeyRotation) -> SendResult<()> {
+        for key in &rotation.superseded {
+            self.delete_api_key(&key.id)?;
+        }
+        Ok(())
+    }
+
+    pub async fn async_list_api_keys(
+        &self,
+        domain: Option<&str>,
+        kind: Option<KeyKind>,
+    ) -> SendResult<Vec<ApiKey>> {
+        let list: KeyList = Mailgun::async_json(
+            self.async_request(Method::GET, keys_url(&self.region, None))?
+                .query(&list_query(domain, kind.as_ref())),
+        )
+        .await?;
+        Ok(list.items)
+    }
+
+    pub async fn async_create_api_key(&self, key: &NewApiKey) -> SendResult<CreatedApiKey> {
+        let envelope: CreatedKeyEnvelope = Mailgun::async_json(
+            self.async_request(Method::POST, keys_url(&self.region, None))?
+                .form(&key.params()),
+        )
+        .await?;
+        Ok(envelope.into())
+    }
+
+    pub async fn async_delete_api_key(&self, id: &str) -> SendResult<ApiKeyResponse> {
+        Mailgun::async_json(self.async_request(Method::DELETE, keys_url(&self.region, Some(id)))?)
+            .await
+    }
+
+    pub async fn async_provision_sending_key(
+        &self,
+        domain: &str,
+        description: &str,
+        expiration: Option<Duration>,
+    ) -> SendResult<KeyRotation> {
+        let existing = self
+            .async_list_api_keys(Some(domain), Some(KeyKind::Domain))
+            .await?;
+        let key = self
+            .async_create_api_key(&NewApiKey::sending(domain, description, expiration))
+            .await?;
+        Ok(KeyRotation {
+            superseded: superseded(existing, &key),
+            key,
+        })
+    }
+
+    pub async fn async_retire_superseded_keys(&self, rotation: &KeyRotation) -> SendResult<()> {
+        for key in &rotation.superseded {
+            self.async_delete_api_key(&key.id).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn created_key_should_keep_its_secret_out_of_debug() {
+        let envelope: CreatedKeyEnvelope = serde_json::from_str(
+            r#"{
+                "message": "great success",
+                "key": {
+                    "id": "d0d54a3c-7f4e4b3a",
+                    "description": "billing-service",
+                    "kind": "domain",
+                    "role": "sending",
+                    "domain_name": "mg.example.com",
+                    "created_at": "2025-01-07T11:23:27Z",
+                    "expires_at": null,
+                    "is_disabled": false,
+                    "secret": "3f0e5a8c1d2b-sending"
+                }
+            }"#,
+        )
+        .unwrap();
+        let created = CreatedApiKey::from(envelope);
+        assert_eq!(created.secret.expose_secret(), "3f0e5a8c1d2b-sending");
+        assert!(!format!("{:?}", created).contains("3f0e5a8c1d2b"));
+
+        let old = ApiKey {
+            id: "old".to_string(),
+            ..created.key.clone()
+        };
+        let other_service = ApiKey {
+            id: "other".to_string(),
+            description: "mailer".to_string(),
+            ..created.key.clone()
+        };
+        assert_eq!(
+            superseded(
+                vec![old.clone(), other_service, created.key.clone()],
+                &created
+            ),
+            vec![old]
+        );
+    }
+
+    #[test]
+    fn unknown_roles_should_be_sent_back_unchanged() {
+        let role: KeyRole = serde_json::from_str(r#""analyst""#).unwrap();
+        assert_eq!(role, KeyRole::Other("analyst".to_string()));
+
+        let key = NewApiKey::builder()
+            .role(role)
+            .kind(KeyKind::from("service".to_string()))
+            .build();
+        assert_eq!(
+            key.params(),
+            vec![
+                ("role", "analyst".to_string()),
+                ("kind", "service".to_string()),
+            ]
+        );
+    }
+}
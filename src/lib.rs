@@ -1,16 +1,39 @@
-use reqwest::Error as ReqError;
+use reqwest::{Error as ReqError, IntoUrl, Method, Url};
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::fmt;
+use std::sync::Arc;
 use thiserror::Error;
 use typed_builder::TypedBuilder;
 
-const MESSAGES_ENDPOINT: &str = "messages";
+pub mod address;
+pub mod credentials;
+pub mod dkim;
+pub mod dns;
+pub mod domains;
+pub mod events;
+pub mod inbound;
+pub mod ips;
+pub mod keys;
+pub mod mailing_lists;
+pub mod region;
+pub mod routes;
+pub mod secret;
+pub mod stored_messages;
+pub mod subaccounts;
+pub mod suppression_cache;
+pub mod suppressions;
+pub mod tracking;
+pub mod validation;
+pub mod webhook_receiver;
+pub mod webhooks;
+
+pub use region::{CustomHost, MailgunRegion, ParseRegionError};
+pub use secret::{KeyProvider, KeyProviderError, Secret};
 
-pub enum MailgunRegion {
-    US,
-    EU,
-}
+const MESSAGES_ENDPOINT: &str = "messages";
+const ON_BEHALF_OF_HEADER: &str = "X-Mailgun-On-Behalf-Of";
 
 #[derive(Debug, Clone)]
 pub enum AttachmentType {
@@ -44,17 +67,103 @@ impl From<&str> for Attachment {
     }
 }
 
-fn get_base_url(region: MailgunRegion) -> &'static str {
-    match region {
-        MailgunRegion::US => "https://api.mailgun.net/v3",
-        MailgunRegion::EU => "https://api.eu.mailgun.net/v3",
+fn get_base_url(region: &MailgunRegion) -> Url {
+    versioned_url(region, "v3", &[])
+}
+
+/// URL of an endpoint outside the v3 API, e.g. `version` `"v4"`.
+fn versioned_url(region: &MailgunRegion, version: &str, segments: &[&str]) -> Url {
+    let mut path = vec![version];
+    path.extend(segments);
+    build_url(region.api_host(), &path)
+}
+
+/// Appends percent-encoded path segments to `base`, so addresses and other
+/// user input can be used in a URL path safely.
+fn build_url(mut base: Url, segments: &[&str]) -> Url {
+    base.path_segments_mut()
+        .expect("base url can have path segments")
+        .pop_if_empty()
+        .extend(segments);
+    base
+}
+
+/// A query parameter that is left out when `value` is `None`.
+fn optional_query<T: ToString>(
+    name: &'static str,
+    value: Option<T>,
+) -> Vec<(&'static str, String)> {
+    value
+        .map(|value| vec![(name, value.to_string())])
+        .unwrap_or_default()
+}
+
+/// The `limit` query parameter of a listing, left out to use Mailgun's
+/// default page size.
+fn limit_query(limit: Option<usize>) -> Vec<(&'static str, String)> {
+    optional_query("limit", limit)
+}
+
+/// How Mailgun spells booleans in some parameters.
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
     }
 }
 
-#[derive(Default, Debug)]
+/// Adds the file at `path` to `form`, streaming it from disk.
+fn blocking_file_part(
+    form: reqwest::blocking::multipart::Form,
+    field_name: &'static str,
+    path: &str,
+) -> SendResult<reqwest::blocking::multipart::Form> {
+    form.file(field_name, path)
+        .map_err(|err| SendError::IoWithPath {
+            path: path.to_string(),
+            source: err,
+        })
+}
+
+async fn async_file_part(
+    form: reqwest::multipart::Form,
+    field_name: &'static str,
+    path: &str,
+) -> SendResult<reqwest::multipart::Form> {
+    form.file(field_name, path)
+        .await
+        .map_err(|err| SendError::IoWithPath {
+            path: path.to_string(),
+            source: err,
+        })
+}
+
+#[derive(Default, Clone)]
 pub struct Mailgun {
-    pub api_key: String,
+    pub api_key: Secret,
     pub domain: String,
+    /// Region every request goes to, see [`Mailgun::in_region`] to
+    /// override it for a single call.
+    pub region: MailgunRegion,
+    /// Subaccount id every request is made on behalf of, see
+    /// [`Mailgun::on_behalf_of`].
+    pub subaccount: Option<String>,
+    /// Used instead of `api_key` when set, see
+    /// [`Mailgun::with_key_provider`].
+    pub key_provider: Option<Arc<dyn KeyProvider>>,
+}
+
+impl fmt::Debug for Mailgun {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Mailgun")
+            .field("api_key", &self.api_key)
+            .field("domain", &self.domain)
+            .field("region", &self.region)
+            .field("subaccount", &self.subaccount)
+            .field("key_provider", &self.key_provider.is_some())
+            .finish()
+    }
 }
 
 #[derive(Debug, Error)]
@@ -68,6 +177,33 @@ pub enum SendError {
         #[source]
         source: std::io::Error,
     },
+
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// A batched upload failed part way; the first `applied` items were
+    /// uploaded and only the rest need retrying.
+    #[error("batch failed after {applied} items were applied: {source}")]
+    PartialBatch {
+        applied: usize,
+        #[source]
+        source: Box<SendError>,
+    },
+
+    #[error("recipients are suppressed: {}", addresses.join(", "))]
+    Suppressed { addresses: Vec<String> },
+
+    #[error("job `{job}` has no results yet")]
+    JobNotFinished { job: String },
+
+    #[error("cannot get the API key: {0}")]
+    KeyProvider(#[source] KeyProviderError),
+
+    #[error("refusing to send the API key to untrusted url `{url}`")]
+    UntrustedUrl { url: String },
 }
 
 pub type SendResult<T> = Result<T, SendError>;
@@ -78,15 +214,117 @@ pub struct SendResponse {
     pub id: String,
 }
 
+/// One page of a paginated list endpoint.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub paging: Paging,
+}
+
+/// Absolute URLs of the neighbouring pages, as returned by Mailgun.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Paging {
+    pub first: String,
+    pub last: String,
+    pub next: String,
+    pub previous: String,
+}
+
 impl Mailgun {
+    /// A copy of the client that acts on a subaccount, using the primary
+    /// account's key. Scope a single call with
+    /// `client.on_behalf_of(id).send(...)`.
+    pub fn on_behalf_of(&self, subaccount_id: &str) -> Mailgun {
+        Mailgun {
+            subaccount: Some(subaccount_id.to_string()),
+            ..self.clone()
+        }
+    }
+
+    /// A copy of the client that calls `region` instead, e.g.
+    /// `client.in_region(MailgunRegion::EU).send(...)`.
+    pub fn in_region(&self, region: MailgunRegion) -> Mailgun {
+        Mailgun {
+            region,
+            ..self.clone()
+        }
+    }
+
+    /// Takes the API key from `provider` on every request, e.g. one that
+    /// reads it from a vault and picks up rotated keys.
+    pub fn with_key_provider(self, provider: impl KeyProvider + 'static) -> Mailgun {
+        Mailgun {
+            key_provider: Some(Arc::new(provider)),
+            ..self
+        }
+    }
+
+    fn current_api_key(&self) -> SendResult<Secret> {
+        match self.key_provider {
+            Some(ref provider) => provider.api_key().map_err(SendError::KeyProvider),
+            None => Ok(self.api_key.clone()),
+        }
+    }
+
+    fn blocking_request(
+        &self,
+        method: Method,
+        url: impl IntoUrl,
+    ) -> SendResult<reqwest::blocking::RequestBuilder> {
+        let request = reqwest::blocking::Client::new()
+            .request(method, url)
+            .basic_auth("api", Some(self.current_api_key()?.expose_secret()));
+        Ok(match self.subaccount {
+            Some(ref id) => request.header(ON_BEHALF_OF_HEADER, id),
+            None => request,
+        })
+    }
+
+    fn async_request(
+        &self,
+        method: Method,
+        url: impl IntoUrl,
+    ) -> SendResult<reqwest::RequestBuilder> {
+        let request = reqwest::Client::new()
+            .request(method, url)
+            .basic_auth("api", Some(self.current_api_key()?.expose_secret()));
+        Ok(match self.subaccount {
+            Some(ref id) => request.header(ON_BEHALF_OF_HEADER, id),
+            None => request,
+        })
+    }
+
+    fn blocking_json<T: DeserializeOwned>(
+        request: reqwest::blocking::RequestBuilder,
+    ) -> SendResult<T> {
+        let res = request.send()?.error_for_status()?;
+        Ok(res.json()?)
+    }
+
+    async fn async_json<T: DeserializeOwned>(request: reqwest::RequestBuilder) -> SendResult<T> {
+        let res = request.send().await?.error_for_status()?;
+        Ok(res.json().await?)
+    }
+
+    /// Fetches the page following `page`. Mailgun always returns a `next`
+    /// link, so an empty `items` marks the end of the list.
+    pub fn next_page<T: DeserializeOwned>(&self, page: &Page<T>) -> SendResult<Page<T>> {
+        Mailgun::blocking_json(self.blocking_request(Method::GET, &page.paging.next)?)
+    }
+
+    pub async fn async_next_page<T: DeserializeOwned>(
+        &self,
+        page: &Page<T>,
+    ) -> SendResult<Page<T>> {
+        Mailgun::async_json(self.async_request(Method::GET, &page.paging.next)?).await
+    }
+
     pub fn send(
         &self,
-        region: MailgunRegion,
         sender: &EmailAddress,
         message: Message,
         attachments: Option<Vec<Attachment>>,
     ) -> SendResult<SendResponse> {
-        let client = reqwest::blocking::Client::new();
         let mut params = message.params();
         params.insert("from".to_string(), sender.to_string());
 
@@ -102,24 +340,16 @@ impl Mailgun {
                 AttachmentType::Inline => "inline",
             };
 
-            form =
-                form.file(field_name, &attachment.path)
-                    .map_err(|err| SendError::IoWithPath {
-                        path: attachment.path.clone(),
-                        source: err,
-                    })?;
+            form = blocking_file_part(form, field_name, &attachment.path)?;
         }
 
-        let url = format!(
-            "{}/{}/{}",
-            get_base_url(region),
-            self.domain,
-            MESSAGES_ENDPOINT
+        let url = build_url(
+            get_base_url(&self.region),
+            &[&self.domain, MESSAGES_ENDPOINT],
         );
 
-        let res = client
-            .post(url)
-            .basic_auth("api", Some(self.api_key.clone()))
+        let res = self
+            .blocking_request(Method::POST, url)?
             .multipart(form)
             .send()?
             .error_for_status()?;
@@ -130,12 +360,10 @@ impl Mailgun {
 
     pub async fn async_send(
         &self,
-        region: MailgunRegion,
         sender: &EmailAddress,
         message: Message,
         attachments: Option<Vec<Attachment>>,
     ) -> SendResult<SendResponse> {
-        let client = reqwest::Client::new();
         let mut params = message.params();
         params.insert("from".to_string(), sender.to_string());
 
@@ -151,25 +379,16 @@ impl Mailgun {
                 AttachmentType::Inline => "inline",
             };
 
-            form = form
-                .file(field_name, &attachment.path)
-                .await
-                .map_err(|err| SendError::IoWithPath {
-                    path: attachment.path.clone(),
-                    source: err,
-                })?;
+            form = async_file_part(form, field_name, &attachment.path).await?;
         }
 
-        let url = format!(
-            "{}/{}/{}",
-            get_base_url(region),
-            self.domain,
-            MESSAGES_ENDPOINT
+        let url = build_url(
+            get_base_url(&self.region),
+            &[&self.domain, MESSAGES_ENDPOINT],
         );
 
-        let res = client
-            .post(url)
-            .basic_auth("api", Some(self.api_key.clone()))
+        let res = self
+            .async_request(Method::POST, url)?
             .multipart(form)
             .send()
             .await?
@@ -200,6 +419,9 @@ pub struct Message {
     pub template_vars: HashMap<String, String>,
     #[builder(default)]
     pub template_json: Option<serde_json::Value>,
+    /// Send through this IP pool instead of the domain's IPs.
+    #[builder(default, setter(strip_option, into))]
+    pub sending_ip_pool: Option<String>,
 }
 
 impl Message {
@@ -231,6 +453,10 @@ impl Message {
             }
         }
 
+        if let Some(pool) = self.sending_ip_pool {
+            params.insert(String::from("o:sending-ip-pool"), pool);
+        }
+
         params
     }
 
@@ -275,8 +501,11 @@ impl EmailAddress {
 impl fmt::Display for EmailAddress {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.name {
-            Some(ref name) => write!(f, "{} <{}>", name, self.address),
-            None => write!(f, "{}", self.address),
+            Some(ref name) if !name.is_empty() => {
+                address::write_display_name(f, name)?;
+                write!(f, " <{}>", self.address)
+            }
+            _ => write!(f, "{}", self.address),
         }
     }
 }
@@ -326,7 +555,57 @@ mod tests {
                 template: "template".to_string(),
                 template_vars: [("name".into(), "value".into())].iter().cloned().collect(),
                 template_json: None,
+                sending_ip_pool: None,
             }
         );
     }
+
+    #[test]
+    fn sending_ip_pool_should_only_be_sent_when_set() {
+        let message = Message::builder()
+            .to(vec!["example@example.com".into()])
+            .subject("Hello")
+            .build();
+        assert!(!message.clone().params().contains_key("o:sending-ip-pool"));
+
+        let message = Message {
+            sending_ip_pool: Some("transactional".to_string()),
+            ..message
+        };
+        assert_eq!(
+            message
+                .params()
+                .get("o:sending-ip-pool")
+                .map(String::as_str),
+            Some("transactional")
+        );
+    }
+
+    #[test]
+    fn api_key_should_only_reach_the_auth_header() {
+        let client = Mailgun {
+            api_key: "key-static".into(),
+            domain: "example.com".to_string(),
+            ..Default::default()
+        };
+        assert!(!format!("{:?}", client).contains("key-static"));
+
+        let client = client.with_key_provider(|| Ok(Secret::from("key-rotated")));
+        let request = client
+            .blocking_request(Method::GET, "https://api.mailgun.net/v3/domains")
+            .unwrap()
+            .build()
+            .unwrap();
+        // "api:key-rotated"
+        assert_eq!(
+            request.headers()[reqwest::header::AUTHORIZATION],
+            "Basic YXBpOmtleS1yb3RhdGVk"
+        );
+
+        let client = client.with_key_provider(|| Err("vault is sealed".into()));
+        match client.blocking_request(Method::GET, "https://api.mailgun.net/v3/domains") {
+            Err(SendError::KeyProvider(err)) => assert_eq!(err.to_string(), "vault is sealed"),
+            other => panic!("request was built without a key: {:?}", other.map(|_| ())),
+        }
+    }
 }
@@ -0,0 +1,526 @@
+use crate::suppressions::CsvSource;
+use crate::{
+    build_url, get_base_url, limit_query, yes_no, EmailAddress, Mailgun, Page, SendError,
+    SendResult,
+};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use typed_builder::TypedBuilder;
+
+const LISTS_ENDPOINT: &str = "lists";
+const PAGES_ENDPOINT: &str = "pages";
+const MEMBERS_ENDPOINT: &str = "members";
+const MEMBERS_JSON_ENDPOINT: &str = "members.json";
+const MEMBERS_CSV_ENDPOINT: &str = "members.csv";
+
+/// Most members Mailgun accepts in one bulk upload.
+pub const MAX_BULK_MEMBERS: usize = 1000;
+
+/// Who may post to a mailing list.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessLevel {
+    /// Only the API can send to the list.
+    Readonly,
+    /// Members can send to the list.
+    Members,
+    /// Anyone can send to the list.
+    Everyone,
+}
+
+impl AccessLevel {
+    fn as_str(self) -> &'static str {
+        match self {
+            AccessLevel::Readonly => "readonly",
+            AccessLevel::Members => "members",
+            AccessLevel::Everyone => "everyone",
+        }
+    }
+}
+
+/// Where replies to list messages go.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplyPreference {
+    List,
+    Sender,
+}
+
+impl ReplyPreference {
+    fn as_str(self) -> &'static str {
+        match self {
+            ReplyPreference::List => "list",
+            ReplyPreference::Sender => "sender",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct MailingList {
+    pub address: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub access_level: AccessLevel,
+    pub reply_preference: Option<ReplyPreference>,
+    #[serde(default)]
+    pub members_count: u64,
+    pub created_at: String,
+}
+
+/// Sends to the list when used as a `Message.to` entry.
+impl From<&MailingList> for EmailAddress {
+    fn from(list: &MailingList) -> Self {
+        EmailAddress::address(&list.address)
+    }
+}
+
+/// Fields of a list to create, or to change on update. Unset fields keep
+/// Mailgun's defaults or current values.
+#[derive(TypedBuilder, Debug, PartialEq, Eq, Clone)]
+pub struct NewMailingList {
+    #[builder(setter(into))]
+    pub address: String,
+    #[builder(default, setter(strip_option, into))]
+    pub name: Option<String>,
+    #[builder(default, setter(strip_option, into))]
+    pub description: Option<String>,
+    #[builder(default, setter(strip_option))]
+    pub access_level: Option<AccessLevel>,
+    #[builder(default, setter(strip_option))]
+    pub reply_preference: Option<ReplyPreference>,
+}
+
+impl NewMailingList {
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![("address", self.address.clone())];
+        if let Some(ref name) = self.name {
+            params.push(("name", name.clone()));
+        }
+        if let Some(ref description) = self.description {
+            params.push(("description", description.clone()));
+        }
+        if let Some(access_level) = self.access_level {
+            params.push(("access_level", access_level.as_str().to_string()));
+        }
+        if let Some(reply_preference) = self.reply_preference {
+            params.push(("reply_preference", reply_preference.as_str().to_string()));
+        }
+        params
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+pub struct ListMember {
+    pub address: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub vars: serde_json::Value,
+    pub subscribed: bool,
+}
+
+#[derive(TypedBuilder, Serialize, Debug, PartialEq, Clone)]
+pub struct NewListMember {
+    #[builder(setter(into))]
+    pub address: String,
+    #[builder(default, setter(strip_option, into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Custom data for templates, e.g. `%recipient.first_name%`.
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vars: Option<serde_json::Value>,
+    #[builder(default, setter(strip_option))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subscribed: Option<bool>,
+}
+
+impl NewListMember {
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![("address", self.address.clone())];
+        if let Some(ref name) = self.name {
+            params.push(("name", name.clone()));
+        }
+        if let Some(ref vars) = self.vars {
+            params.push(("vars", vars.to_string()));
+        }
+        if let Some(subscribed) = self.subscribed {
+            params.push(("subscribed", yes_no(subscribed).to_string()));
+        }
+        params
+    }
+}
+
+impl From<&str> for NewListMember {
+    fn from(address: &str) -> Self {
+        NewListMember {
+            address: address.to_string(),
+            name: None,
+            vars: None,
+            subscribed: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct MailingListResponse {
+    pub message: String,
+    pub list: MailingList,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+pub struct ListMemberResponse {
+    pub message: String,
+    pub member: ListMember,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct DeleteResponse {
+    pub message: String,
+}
+
+/// Response to a bulk upload, which Mailgun processes as a background task.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct BulkMembersResponse {
+    pub message: String,
+    #[serde(rename = "task-id", default)]
+    pub task_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ListEnvelope {
+    list: MailingList,
+}
+
+#[derive(Deserialize)]
+struct MemberEnvelope {
+    member: ListMember,
+}
+
+fn bulk_members_params(
+    members: &[NewListMember],
+    upsert: bool,
+) -> SendResult<Vec<(&'static str, String)>> {
+    let members = serde_json::to_string(members)?;
+    Ok(vec![
+        ("members", members),
+        ("upsert", yes_no(upsert).to_string()),
+    ])
+}
+
+/// The error of the batch after `batches_done` successful ones.
+fn partial_batch(batches_done: usize, err: SendError) -> SendError {
+    if batches_done == 0 {
+        return err;
+    }
+    SendError::PartialBatch {
+        applied: batches_done * MAX_BULK_MEMBERS,
+        source: Box::new(err),
+    }
+}
+
+impl Mailgun {
+    fn lists_url(&self, segments: &[&str]) -> reqwest::Url {
+        let mut path = vec![LISTS_ENDPOINT];
+        path.extend(segments);
+        build_url(get_base_url(&self.region), &path)
+    }
+
+    pub fn list_mailing_lists(&self, limit: Option<usize>) -> SendResult<Page<MailingList>> {
+        let url = self.lists_url(&[PAGES_ENDPOINT]);
+        Mailgun::blocking_json(
+            self.blocking_request(Method::GET, url)?
+                .query(&limit_query(limit)),
+        )
+    }
+
+    pub fn get_mailing_list(&self, address: &str) -> SendResult<MailingList> {
+        let url = self.lists_url(&[address]);
+        let envelope: ListEnvelope =
+            Mailgun::blocking_json(self.blocking_request(Method::GET, url)?)?;
+        Ok(envelope.list)
+    }
+
+    pub fn create_mailing_list(&self, list: &NewMailingList) -> SendResult<MailingListResponse> {
+        let url = self.lists_url(&[]);
+        Mailgun::blocking_json(
+            self.blocking_request(Method::POST, url)?
+                .form(&list.params()),
+        )
+    }
+
+    /// Updates the list at `address`; `list.address` renames it.
+    pub fn update_mailing_list(
+        &self,
+        address: &str,
+        list: &NewMailingList,
+    ) -> SendResult<MailingListResponse> {
+        let url = self.lists_url(&[address]);
+        Mailgun::blocking_json(
+            self.blocking_request(Method::PUT, url)?
+                .form(&list.params()),
+        )
+    }
+
+    pub fn delete_mailing_list(&self, address: &str) -> SendResult<DeleteResponse> {
+        let url = self.lists_url(&[address]);
+        Mailgun::blocking_json(self.blocking_request(Method::DELETE, url)?)
+    }
+
+    pub fn list_members(&self, list: &str, limit: Option<usize>) -> SendResult<Page<ListMember>> {
+        let url = self.lists_url(&[list, MEMBERS_ENDPOINT, PAGES_ENDPOINT]);
+        Mailgun::blocking_json(
+            self.blocking_request(Method::GET, url)?
+                .query(&limit_query(limit)),
+        )
+    }
+
+    pub fn get_member(&self, list: &str, address: &str) -> SendResult<ListMember> {
+        let url = self.lists_url(&[list, MEMBERS_ENDPOINT, address]);
+        let envelope: MemberEnvelope =
+            Mailgun::blocking_json(self.blocking_request(Method::GET, url)?)?;
+        Ok(envelope.member)
+    }
+
+    /// Adds a member, or with `upsert` updates it if it already exists.
+    pub fn add_member(
+        &self,
+        list: &str,
+        member: &NewListMember,
+        upsert: bool,
+    ) -> SendResult<ListMemberResponse> {
+        let url = self.lists_url(&[list, MEMBERS_ENDPOINT]);
+        let mut params = member.params();
+        params.push(("upsert", yes_no(upsert).to_string()));
+        Mailgun::blocking_json(self.blocking_request(Method::POST, url)?.form(&params))
+    }
+
+    pub fn update_member(
+        &self,
+        list: &str,
+        address: &str,
+        member: &NewListMember,
+    ) -> SendResult<ListMemberResponse> {
+        let url = self.lists_url(&[list, MEMBERS_ENDPOINT, address]);
+        Mailgun::blocking_json(
+            self.blocking_request(Method::PUT, url)?
+                .form(&member.params()),
+        )
+    }
+
+    pub fn delete_member(&self, list: &str, address: &str) -> SendResult<DeleteResponse> {
+        let url = self.lists_url(&[list, MEMBERS_ENDPOINT, address]);
+        Mailgun::blocking_json(self.blocking_request(Method::DELETE, url)?)
+    }
+
+    /// Uploads members as JSON, in batches of [`MAX_BULK_MEMBERS`], and
+    /// returns one response per batch. If a batch fails after others went
+    /// through, the error is a [`SendError::PartialBatch`] telling how many
+    /// members were applied.
+    pub fn add_members(
+        &self,
+        list: &str,
+        members: &[NewListMember],
+        upsert: bool,
+    ) -> SendResult<Vec<BulkMembersResponse>> {
+        let url = self.lists_url(&[list, MEMBERS_JSON_ENDPOINT]);
+        let mut responses = Vec::new();
+        for batch in members.chunks(MAX_BULK_MEMBERS) {
+            let response = bulk_members_params(batch, upsert).and_then(|params| {
+                Mailgun::blocking_json(
+                    self.blocking_request(Method::POST, url.clone())?
+                        .form(&params),
+                )
+            });
+            responses.push(response.map_err(|err| partial_batch(responses.len(), err))?);
+        }
+        Ok(responses)
+    }
+
+    /// Uploads a CSV of members with an `address` column and optionally
+    /// `name`, `vars` and `subscribed` columns.
+    pub fn import_members(
+        &self,
+        list: &str,
+        csv: CsvSource,
+        upsert: bool,
+    ) -> SendResult<BulkMembersResponse> {
+        let url = self.lists_url(&[list, MEMBERS_CSV_ENDPOINT]);
+        let form = csv
+            .blocking_form("members")?
+            .text("upsert", yes_no(upsert).to_string());
+        Mailgun::blocking_json(self.blocking_request(Method::POST, url)?.multipart(form))
+    }
+
+    pub async fn async_list_mailing_lists(
+        &self,
+        limit: Option<usize>,
+    ) -> SendResult<Page<MailingList>> {
+        let url = self.lists_url(&[PAGES_ENDPOINT]);
+        Mailgun::async_json(
+            self.async_request(Method::GET, url)?
+                .query(&limit_query(limit)),
+        )
+        .await
+    }
+
+    pub async fn async_get_mailing_list(&self, address: &str) -> SendResult<MailingList> {
+        let url = self.lists_url(&[address]);
+        let envelope: ListEnvelope =
+            Mailgun::async_json(self.async_request(Method::GET, url)?).await?;
+        Ok(envelope.list)
+    }
+
+    pub async fn async_create_mailing_list(
+        &self,
+        list: &NewMailingList,
+    ) -> SendResult<MailingListResponse> {
+        let url = self.lists_url(&[]);
+        Mailgun::async_json(self.async_request(Method::POST, url)?.form(&list.params())).await
+    }
+
+    pub async fn async_update_mailing_list(
+        &self,
+        address: &str,
+        list: &NewMailingList,
+    ) -> SendResult<MailingListResponse> {
+        let url = self.lists_url(&[address]);
+        Mailgun::async_json(self.async_request(Method::PUT, url)?.form(&list.params())).await
+    }
+
+    pub async fn async_delete_mailing_list(&self, address: &str) -> SendResult<DeleteResponse> {
+        let url = self.lists_url(&[address]);
+        Mailgun::async_json(self.async_request(Method::DELETE, url)?).await
+    }
+
+    pub async fn async_list_members(
+        &self,
+        list: &str,
+        limit: Option<usize>,
+    ) -> SendResult<Page<ListMember>> {
+        let url = self.lists_url(&[list, MEMBERS_ENDPOINT, PAGES_ENDPOINT]);
+        Mailgun::async_json(
+            self.async_request(Method::GET, url)?
+                .query(&limit_query(limit)),
+        )
+        .await
+    }
+
+    pub async fn async_get_member(&self, list: &str, address: &str) -> SendResult<ListMember> {
+        let url = self.lists_url(&[list, MEMBERS_ENDPOINT, address]);
+        let envelope: MemberEnvelope =
+            Mailgun::async_json(self.async_request(Method::GET, url)?).await?;
+        Ok(envelope.member)
+    }
+
+    pub async fn async_add_member(
+        &self,
+        list: &str,
+        member: &NewListMember,
+        upsert: bool,
+    ) -> SendResult<ListMemberResponse> {
+        let url = self.lists_url(&[list, MEMBERS_ENDPOINT]);
+        let mut params = member.params();
+        params.push(("upsert", yes_no(upsert).to_string()));
+        Mailgun::async_json(self.async_request(Method::POST, url)?.form(&params)).await
+    }
+
+    pub async fn async_update_member(
+        &self,
+        list: &str,
+        address: &str,
+        member: &NewListMember,
+    ) -> SendResult<ListMemberResponse> {
+        let url = self.lists_url(&[list, MEMBERS_ENDPOINT, address]);
+        Mailgun::async_json(self.async_request(Method::PUT, url)?.form(&member.params())).await
+    }
+
+    pub async fn async_delete_member(
+        &self,
+        list: &str,
+        address: &str,
+    ) -> SendResult<DeleteResponse> {
+        let url = self.lists_url(&[list, MEMBERS_ENDPOINT, address]);
+        Mailgun::async_json(self.async_request(Method::DELETE, url)?).await
+    }
+
+    pub async fn async_add_members(
+        &self,
+        list: &str,
+        members: &[NewListMember],
+        upsert: bool,
+    ) -> SendResult<Vec<BulkMembersResponse>> {
+        let url = self.lists_url(&[list, MEMBERS_JSON_ENDPOINT]);
+        let mut responses = Vec::new();
+        for batch in members.chunks(MAX_BULK_MEMBERS) {
+            let response = match bulk_members_params(batch, upsert) {
+                Ok(params) => {
+                    Mailgun::async_json(
+                        self.async_request(Method::POST, url.clone())?.form(&params),
+                    )
+                    .await
+                }
+                Err(err) => Err(err),
+            };
+            responses.push(response.map_err(|err| partial_batch(responses.len(), err))?);
+        }
+        Ok(responses)
+    }
+
+    pub async fn async_import_members(
+        &self,
+        list: &str,
+        csv: CsvSource,
+        upsert: bool,
+    ) -> SendResult<BulkMembersResponse> {
+        let url = self.lists_url(&[list, MEMBERS_CSV_ENDPOINT]);
+        let form = csv
+            .async_form("members")
+            .await?
+            .text("upsert", yes_no(upsert).to_string());
+        Mailgun::async_json(self.async_request(Method::POST, url)?.multipart(form)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bulk_members_should_serialize_as_json_form_field() {
+        let members = vec![
+            NewListMember::builder()
+                .address("jane@example.com")
+                .name("Jane")
+                .vars(serde_json::json!({"plan": "pro"}))
+                .build(),
+            "john@example.com".into(),
+        ];
+        assert_eq!(
+            bulk_members_params(&members, true).unwrap(),
+            vec![
+                (
+                    "members",
+                    r#"[{"address":"jane@example.com","name":"Jane","vars":{"plan":"pro"}},{"address":"john@example.com"}]"#
+                        .to_string()
+                ),
+                ("upsert", "yes".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn failed_batches_should_report_members_already_applied() {
+        let failure = || SendError::Io(std::io::Error::other("connection reset"));
+        match partial_batch(0, failure()) {
+            SendError::Io(_) => {}
+            other => panic!("first batch failure was wrapped: {:?}", other),
+        }
+        match partial_batch(2, failure()) {
+            SendError::PartialBatch { applied, .. } => assert_eq!(applied, 2 * MAX_BULK_MEMBERS),
+            other => panic!("expected a partial batch error: {:?}", other),
+        }
+    }
+}
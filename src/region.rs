@@ -0,0 +1,122 @@
+use reqwest::Url;
+use serde::de::{self, Deserialize, Deserializer};
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+const US_HOST: &str = "https://api.mailgun.net";
+const EU_HOST: &str = "https://api.eu.mailgun.net";
+
+/// Where the account's data lives, and so which API host to call.
+///
+/// Parses from and serializes to `"us"`, `"eu"` or the URL of a custom host,
+/// so it can be read from config files.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum MailgunRegion {
+    #[default]
+    US,
+    EU,
+    /// Another API host, e.g. a proxy or a mock server in tests, made with
+    /// [`MailgunRegion::custom`]. API versions and endpoints are appended to
+    /// its path.
+    Custom(CustomHost),
+}
+
+/// An http(s) URL with a host, checked by [`MailgunRegion::custom`] so
+/// endpoint paths can always be appended to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomHost(Url);
+
+impl CustomHost {
+    pub fn url(&self) -> &Url {
+        &self.0
+    }
+}
+
+impl MailgunRegion {
+    /// A custom API host. It must be an http(s) URL with a host.
+    pub fn custom(url: Url) -> Result<Self, ParseRegionError> {
+        if (url.scheme() == "https" || url.scheme() == "http") && url.has_host() {
+            Ok(MailgunRegion::Custom(CustomHost(url)))
+        } else {
+            Err(ParseRegionError(url.to_string()))
+        }
+    }
+
+    /// The host the API versions live under.
+    pub(crate) fn api_host(&self) -> Url {
+        match self {
+            MailgunRegion::US => Url::parse(US_HOST).expect("US host is valid"),
+            MailgunRegion::EU => Url::parse(EU_HOST).expect("EU host is valid"),
+            MailgunRegion::Custom(host) => host.0.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("unknown Mailgun region `{0}`, expected \"us\", \"eu\" or an http(s) URL")]
+pub struct ParseRegionError(String);
+
+impl FromStr for MailgunRegion {
+    type Err = ParseRegionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("us") {
+            return Ok(MailgunRegion::US);
+        }
+        if s.eq_ignore_ascii_case("eu") {
+            return Ok(MailgunRegion::EU);
+        }
+        let url = Url::parse(s).map_err(|_| ParseRegionError(s.to_string()))?;
+        MailgunRegion::custom(url)
+    }
+}
+
+impl fmt::Display for MailgunRegion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MailgunRegion::US => f.write_str("us"),
+            MailgunRegion::EU => f.write_str("eu"),
+            MailgunRegion::Custom(host) => write!(f, "{}", host.0),
+        }
+    }
+}
+
+impl Serialize for MailgunRegion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for MailgunRegion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regions_should_round_trip_through_config_strings() {
+        assert_eq!("EU".parse(), Ok(MailgunRegion::EU));
+        let custom: MailgunRegion = serde_json::from_str(r#""http://localhost:8025/""#).unwrap();
+        assert_eq!(
+            custom,
+            MailgunRegion::custom(Url::parse("http://localhost:8025").unwrap()).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_string(&custom).unwrap(),
+            r#""http://localhost:8025/""#
+        );
+        assert_eq!(
+            serde_json::to_string(&MailgunRegion::US).unwrap(),
+            r#""us""#
+        );
+        assert!("mars".parse::<MailgunRegion>().is_err());
+        assert!(MailgunRegion::custom(Url::parse("mailto:x").unwrap()).is_err());
+    }
+}
@@ -0,0 +1,338 @@
+use crate::{build_url, get_base_url, Mailgun, MailgunRegion, SendResult};
+use reqwest::Method;
+use serde::Deserialize;
+use std::fmt;
+use typed_builder::TypedBuilder;
+
+const ROUTES_ENDPOINT: &str = "routes";
+
+/// Writes `value` as a double-quoted route expression string literal.
+///
+/// Mailgun hands the literal to its regex engine as written; its route docs
+/// use `match_recipient("^chris\+(.*)@example.com$")` with a single
+/// backslash. So backslashes are left alone and only `"` is escaped, as
+/// `\"`, which a regex matches as a plain `"` too.
+fn quoted(f: &mut fmt::Formatter, value: &str) -> fmt::Result {
+    write!(f, "\"{}\"", value.replace('"', "\\\""))
+}
+
+/// A single condition of a route expression.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Filter {
+    MatchRecipient(String),
+    MatchHeader { name: String, regex: String },
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Filter::MatchRecipient(regex) => {
+                f.write_str("match_recipient(")?;
+                quoted(f, regex)?;
+            }
+            Filter::MatchHeader { name, regex } => {
+                f.write_str("match_header(")?;
+                quoted(f, name)?;
+                f.write_str(", ")?;
+                quoted(f, regex)?;
+            }
+        }
+        f.write_str(")")
+    }
+}
+
+/// Which messages a route applies to. Regexes are quoted for you, so pass
+/// them exactly as Mailgun should evaluate them.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Expression {
+    CatchAll,
+    /// All filters must match.
+    All(Vec<Filter>),
+}
+
+impl Expression {
+    pub fn catch_all() -> Self {
+        Expression::CatchAll
+    }
+
+    pub fn match_recipient(regex: &str) -> Self {
+        Expression::All(vec![Filter::MatchRecipient(regex.to_string())])
+    }
+
+    pub fn match_header(name: &str, regex: &str) -> Self {
+        Expression::All(vec![Filter::MatchHeader {
+            name: name.to_string(),
+            regex: regex.to_string(),
+        }])
+    }
+
+    /// Requires both expressions to match. A catch-all matches everything,
+    /// so it drops out of the combination.
+    pub fn and(self, other: Expression) -> Self {
+        match (self, other) {
+            (Expression::CatchAll, other) | (other, Expression::CatchAll) => other,
+            (Expression::All(mut filters), Expression::All(more)) => {
+                filters.extend(more);
+                Expression::All(filters)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expression::CatchAll => f.write_str("catch_all()"),
+            Expression::All(filters) => {
+                for (i, filter) in filters.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" and ")?;
+                    }
+                    write!(f, "{filter}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// What a route does with a matching message.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Action {
+    /// Forward to an email address or POST to a URL.
+    Forward(String),
+    /// Store the message, optionally notifying a URL.
+    Store { notify: Option<String> },
+    /// Do not evaluate routes with a lower priority.
+    Stop,
+}
+
+impl Action {
+    pub fn forward(destination: &str) -> Self {
+        Action::Forward(destination.to_string())
+    }
+
+    pub fn store(notify: Option<&str>) -> Self {
+        Action::Store {
+            notify: notify.map(str::to_string),
+        }
+    }
+
+    pub fn stop() -> Self {
+        Action::Stop
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Action::Forward(destination) => {
+                f.write_str("forward(")?;
+                quoted(f, destination)?;
+                f.write_str(")")
+            }
+            Action::Store { notify: Some(url) } => {
+                f.write_str("store(notify=")?;
+                quoted(f, url)?;
+                f.write_str(")")
+            }
+            Action::Store { notify: None } => f.write_str("store()"),
+            Action::Stop => f.write_str("stop()"),
+        }
+    }
+}
+
+#[derive(TypedBuilder, Debug, PartialEq, Eq, Clone)]
+pub struct NewRoute {
+    /// Lower numbers are evaluated first.
+    #[builder(default)]
+    pub priority: u32,
+    #[builder(default, setter(into))]
+    pub description: String,
+    pub expression: Expression,
+    pub actions: Vec<Action>,
+}
+
+impl NewRoute {
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![
+            ("priority", self.priority.to_string()),
+            ("description", self.description.clone()),
+            ("expression", self.expression.to_string()),
+        ];
+        for action in &self.actions {
+            params.push(("action", action.to_string()));
+        }
+        params
+    }
+}
+
+/// A route as stored by Mailgun, with its expression and actions in their
+/// string form.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Route {
+    pub id: String,
+    pub priority: u32,
+    #[serde(default)]
+    pub description: String,
+    pub expression: String,
+    #[serde(default)]
+    pub actions: Vec<String>,
+    pub created_at: String,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct RouteList {
+    pub total_count: usize,
+    pub items: Vec<Route>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct RouteResponse {
+    pub message: String,
+    pub route: Route,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct DeleteRouteResponse {
+    pub message: String,
+    pub id: String,
+}
+
+#[derive(Deserialize)]
+struct RouteEnvelope {
+    route: Route,
+}
+
+fn routes_url(region: &MailgunRegion, id: Option<&str>) -> reqwest::Url {
+    let mut segments = vec![ROUTES_ENDPOINT];
+    segments.extend(id);
+    build_url(get_base_url(region), &segments)
+}
+
+impl Mailgun {
+    /// Lists the account's routes, skipping the first `skip`.
+    pub fn list_routes(&self, skip: usize, limit: usize) -> SendResult<RouteList> {
+        Mailgun::blocking_json(
+            self.blocking_request(Method::GET, routes_url(&self.region, None))?
+                .query(&[("skip", skip), ("limit", limit)]),
+        )
+    }
+
+    pub fn get_route(&self, id: &str) -> SendResult<Route> {
+        let envelope: RouteEnvelope = Mailgun::blocking_json(
+            self.blocking_request(Method::GET, routes_url(&self.region, Some(id)))?,
+        )?;
+        Ok(envelope.route)
+    }
+
+    pub fn create_route(&self, route: &NewRoute) -> SendResult<RouteResponse> {
+        Mailgun::blocking_json(
+            self.blocking_request(Method::POST, routes_url(&self.region, None))?
+                .form(&route.params()),
+        )
+    }
+
+    /// Replaces every field of the route with those of `route`.
+    pub fn update_route(&self, id: &str, route: &NewRoute) -> SendResult<Route> {
+        Mailgun::blocking_json(
+            self.blocking_request(Method::PUT, routes_url(&self.region, Some(id)))?
+                .form(&route.params()),
+        )
+    }
+
+    pub fn delete_route(&self, id: &str) -> SendResult<DeleteRouteResponse> {
+        Mailgun::blocking_json(
+            self.blocking_request(Method::DELETE, routes_url(&self.region, Some(id)))?,
+        )
+    }
+
+    pub async fn async_list_routes(&self, skip: usize, limit: usize) -> SendResult<RouteList> {
+        Mailgun::async_json(
+            self.async_request(Method::GET, routes_url(&self.region, None))?
+                .query(&[("skip", skip), ("limit", limit)]),
+        )
+        .await
+    }
+
+    pub async fn async_get_route(&self, id: &str) -> SendResult<Route> {
+        let envelope: RouteEnvelope = Mailgun::async_json(
+            self.async_request(Method::GET, routes_url(&self.region, Some(id)))?,
+        )
+        .await?;
+        Ok(envelope.route)
+    }
+
+    pub async fn async_create_route(&self, route: &NewRoute) -> SendResult<RouteResponse> {
+        Mailgun::async_json(
+            self.async_request(Method::POST, routes_url(&self.region, None))?
+                .form(&route.params()),
+        )
+        .await
+    }
+
+    pub async fn async_update_route(&self, id: &str, route: &NewRoute) -> SendResult<Route> {
+        Mailgun::async_json(
+            self.async_request(Method::PUT, routes_url(&self.region, Some(id)))?
+                .form(&route.params()),
+        )
+        .await
+    }
+
+    pub async fn async_delete_route(&self, id: &str) -> SendResult<DeleteRouteResponse> {
+        Mailgun::async_json(self.async_request(Method::DELETE, routes_url(&self.region, Some(id)))?)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn route_params_should_quote_expressions_and_actions() {
+        let route = NewRoute::builder()
+            .priority(1)
+            .description("Support inbox")
+            .expression(
+                Expression::catch_all()
+                    .and(Expression::match_recipient(r"^support\+(.*)@example\.com$"))
+                    .and(Expression::match_header("subject", r#"say "hi""#)),
+            )
+            .actions(vec![
+                Action::forward("https://example.com/inbound"),
+                Action::store(Some("https://example.com/stored")),
+                Action::stop(),
+            ])
+            .build();
+
+        assert_eq!(
+            route.params(),
+            vec![
+                ("priority", "1".to_string()),
+                ("description", "Support inbox".to_string()),
+                (
+                    "expression",
+                    r#"match_recipient("^support\+(.*)@example\.com$") and match_header("subject", "say \"hi\"")"#
+                        .to_string()
+                ),
+                ("action", r#"forward("https://example.com/inbound")"#.to_string()),
+                ("action", r#"store(notify="https://example.com/stored")"#.to_string()),
+                ("action", "stop()".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn quoted_regexes_should_keep_their_escapes() {
+        assert_eq!(
+            Expression::match_recipient(r".*@example\.com")
+                .and(Expression::match_header(
+                    "subject",
+                    r#"\[ticket \d+\] "urgent""#
+                ))
+                .to_string(),
+            r#"match_recipient(".*@example\.com") and match_header("subject", "\[ticket \d+\] \"urgent\"")"#
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use std::fmt;
+use zeroize::Zeroize;
+
+/// A sensitive string such as a password or API key. It prints as
+/// `[REDACTED]` in `Debug`, so it can sit in structs that get logged, and
+/// its memory is zeroed when dropped.
+#[derive(Default, Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Secret(value.into())
+    }
+
+    /// The secret value, for handing to whatever needs it.
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Secret(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Secret(value.to_string())
+    }
+}
+
+/// Why a [`KeyProvider`] could not supply a key, e.g. the vault is down.
+pub type KeyProviderError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Supplies the API key for each request, so a key kept in a vault can be
+/// rotated without rebuilding the client, see
+/// [`Mailgun::with_key_provider`](crate::Mailgun::with_key_provider).
+///
+/// It is called for every request, so implementations should hand out a
+/// cached key and refresh it elsewhere rather than fetch it each time. An
+/// error fails the request with [`SendError::KeyProvider`](crate::SendError::KeyProvider)
+/// before anything is sent.
+pub trait KeyProvider: Send + Sync {
+    fn api_key(&self) -> Result<Secret, KeyProviderError>;
+}
+
+impl<F> KeyProvider for F
+where
+    F: Fn() -> Result<Secret, KeyProviderError> + Send + Sync,
+{
+    fn api_key(&self) -> Result<Secret, KeyProviderError> {
+        self()
+    }
+}
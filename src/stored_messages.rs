@@ -0,0 +1,205 @@
+use crate::events::{Event, Storage};
+use crate::{build_url, get_base_url, EmailAddress, Mailgun, SendError, SendResponse, SendResult};
+use reqwest::header::ACCEPT;
+use reqwest::{Method, Url};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+const DOMAINS_ENDPOINT: &str = "domains";
+const MESSAGES_ENDPOINT: &str = "messages";
+/// Mailgun serves stored messages from regional hosts such as
+/// `storage-us-east4.api.mailgun.net`.
+const STORAGE_HOST_SUFFIX: &str = ".mailgun.net";
+
+/// Where to find a stored message: the storage URL from an event, or just
+/// its storage key on the client's domain.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum StorageRef {
+    Url(String),
+    Key(String),
+}
+
+impl StorageRef {
+    /// The stored copy of an event's message, if Mailgun kept one.
+    pub fn from_event(event: &Event) -> Option<Self> {
+        event.storage.as_ref().map(StorageRef::from)
+    }
+
+    /// The URL to request with the client's credentials. Storage URLs come
+    /// from events, which can be forged, so only the region's API host and
+    /// Mailgun's own HTTPS storage hosts are trusted with the API key.
+    fn url(&self, client: &Mailgun) -> SendResult<Url> {
+        match self {
+            StorageRef::Url(url) => {
+                let untrusted = || SendError::UntrustedUrl { url: url.clone() };
+                let parsed = Url::parse(url).map_err(|_| untrusted())?;
+                let api_host = client.region.api_host();
+                let same_origin = parsed.origin() == api_host.origin();
+                let storage_host = parsed.scheme() == "https"
+                    && parsed
+                        .host_str()
+                        .is_some_and(|host| host.ends_with(STORAGE_HOST_SUFFIX));
+                if same_origin || storage_host {
+                    Ok(parsed)
+                } else {
+                    Err(untrusted())
+                }
+            }
+            StorageRef::Key(key) => Ok(build_url(
+                get_base_url(&client.region),
+                &[DOMAINS_ENDPOINT, &client.domain, MESSAGES_ENDPOINT, key],
+            )),
+        }
+    }
+}
+
+impl From<&Storage> for StorageRef {
+    fn from(storage: &Storage) -> Self {
+        StorageRef::Url(storage.url.clone())
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct StoredAttachment {
+    pub url: String,
+    #[serde(rename = "content-type")]
+    pub content_type: String,
+    pub name: String,
+    pub size: u64,
+}
+
+/// A stored message as parsed by Mailgun. Attachments are links to fetch,
+/// not contents.
+#[derive(Deserialize, Debug, Default, PartialEq, Eq, Clone)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct StoredMessage {
+    pub sender: String,
+    pub from: String,
+    pub subject: String,
+    pub recipients: String,
+    pub body_plain: String,
+    pub body_html: Option<String>,
+    pub stripped_text: String,
+    pub stripped_html: Option<String>,
+    pub stripped_signature: Option<String>,
+    pub message_headers: Vec<(String, String)>,
+    pub attachments: Vec<StoredAttachment>,
+    pub content_id_map: HashMap<String, StoredAttachment>,
+}
+
+#[derive(Deserialize)]
+struct MimeEnvelope {
+    #[serde(rename = "body-mime")]
+    body_mime: String,
+}
+
+fn recipients_param(to: &[EmailAddress]) -> [(&'static str, String); 1] {
+    let joined = to
+        .iter()
+        .map(EmailAddress::to_string)
+        .collect::<Vec<String>>()
+        .join(",");
+    [("to", joined)]
+}
+
+impl Mailgun {
+    pub fn get_stored_message(&self, storage: &StorageRef) -> SendResult<StoredMessage> {
+        let url = storage.url(self)?;
+        Mailgun::blocking_json(self.blocking_request(Method::GET, url)?)
+    }
+
+    /// Fetches the stored message as raw MIME, e.g. to archive or re-parse.
+    pub fn get_stored_message_mime(&self, storage: &StorageRef) -> SendResult<String> {
+        let url = storage.url(self)?;
+        let envelope: MimeEnvelope = Mailgun::blocking_json(
+            self.blocking_request(Method::GET, url)?
+                .header(ACCEPT, "message/rfc2822"),
+        )?;
+        Ok(envelope.body_mime)
+    }
+
+    /// Sends the stored message again, to `to` only.
+    pub fn resend_stored_message(
+        &self,
+        storage: &StorageRef,
+        to: &[EmailAddress],
+    ) -> SendResult<SendResponse> {
+        let url = storage.url(self)?;
+        Mailgun::blocking_json(
+            self.blocking_request(Method::POST, url)?
+                .form(&recipients_param(to)),
+        )
+    }
+
+    pub async fn async_get_stored_message(
+        &self,
+        storage: &StorageRef,
+    ) -> SendResult<StoredMessage> {
+        let url = storage.url(self)?;
+        Mailgun::async_json(self.async_request(Method::GET, url)?).await
+    }
+
+    pub async fn async_get_stored_message_mime(&self, storage: &StorageRef) -> SendResult<String> {
+        let url = storage.url(self)?;
+        let envelope: MimeEnvelope = Mailgun::async_json(
+            self.async_request(Method::GET, url)?
+                .header(ACCEPT, "message/rfc2822"),
+        )
+        .await?;
+        Ok(envelope.body_mime)
+    }
+
+    pub async fn async_resend_stored_message(
+        &self,
+        storage: &StorageRef,
+        to: &[EmailAddress],
+    ) -> SendResult<SendResponse> {
+        let url = storage.url(self)?;
+        Mailgun::async_json(
+            self.async_request(Method::POST, url)?
+                .form(&recipients_param(to)),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn storage_keys_should_resolve_on_the_client_domain() {
+        let client = Mailgun {
+            api_key: Default::default(),
+            domain: "example.com".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            StorageRef::Key("AgEFmOq1".to_string())
+                .url(&client)
+                .unwrap()
+                .as_str(),
+            "https://api.mailgun.net/v3/domains/example.com/messages/AgEFmOq1"
+        );
+    }
+
+    #[test]
+    fn storage_urls_should_only_be_trusted_on_mailgun_hosts() {
+        let client = Mailgun::default();
+        let stored =
+            "https://storage-us-east4.api.mailgun.net/v3/domains/example.com/messages/AgEFmOq1";
+        assert!(StorageRef::Url(stored.to_string()).url(&client).is_ok());
+
+        for forged in [
+            "https://attacker.example/v3/domains/example.com/messages/AgEFmOq1",
+            "https://mailgun.net.attacker.example/messages/AgEFmOq1",
+            "http://storage-us-east4.api.mailgun.net/v3/messages/AgEFmOq1",
+            "not a url",
+        ] {
+            match StorageRef::Url(forged.to_string()).url(&client) {
+                Err(SendError::UntrustedUrl { url }) => assert_eq!(url, forged),
+                other => panic!("forged url was trusted: {:?}", other),
+            }
+        }
+    }
+}
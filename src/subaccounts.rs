@@ -0,0 +1,193 @@
+use crate::{optional_query, versioned_url, Mailgun, MailgunRegion, SendResult};
+use reqwest::Method;
+use serde::Deserialize;
+
+const ACCOUNTS_ENDPOINT: &str = "accounts";
+const SUBACCOUNTS_ENDPOINT: &str = "subaccounts";
+const ENABLE_ENDPOINT: &str = "enable";
+const DISABLE_ENDPOINT: &str = "disable";
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum SubaccountStatus {
+    Open,
+    Disabled,
+    Closed,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Subaccount {
+    /// The id to pass to [`Mailgun::on_behalf_of`].
+    pub id: String,
+    pub name: String,
+    pub status: SubaccountStatus,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct SubaccountList {
+    pub total: usize,
+    pub subaccounts: Vec<Subaccount>,
+}
+
+#[derive(Deserialize)]
+struct SubaccountEnvelope {
+    subaccount: Subaccount,
+}
+
+fn subaccounts_url(region: &MailgunRegion, segments: &[&str]) -> reqwest::Url {
+    let mut path = vec![ACCOUNTS_ENDPOINT, SUBACCOUNTS_ENDPOINT];
+    path.extend(segments);
+    versioned_url(region, "v5", &path)
+}
+
+impl Mailgun {
+    /// Subaccounts are managed from the primary account, so these calls are
+    /// never made on behalf of one.
+    fn primary_account(&self) -> Mailgun {
+        Mailgun {
+            subaccount: None,
+            ..self.clone()
+        }
+    }
+
+    /// Lists subaccounts, skipping the first `skip`, optionally only enabled
+    /// or disabled ones.
+    pub fn list_subaccounts(
+        &self,
+        skip: usize,
+        limit: usize,
+        enabled: Option<bool>,
+    ) -> SendResult<SubaccountList> {
+        Mailgun::blocking_json(
+            self.primary_account()
+                .blocking_request(Method::GET, subaccounts_url(&self.region, &[]))?
+                .query(&[("skip", skip), ("limit", limit)])
+                .query(&optional_query("enabled", enabled)),
+        )
+    }
+
+    pub fn get_subaccount(&self, id: &str) -> SendResult<Subaccount> {
+        let envelope: SubaccountEnvelope = Mailgun::blocking_json(
+            self.primary_account()
+                .blocking_request(Method::GET, subaccounts_url(&self.region, &[id]))?,
+        )?;
+        Ok(envelope.subaccount)
+    }
+
+    pub fn create_subaccount(&self, name: &str) -> SendResult<Subaccount> {
+        let envelope: SubaccountEnvelope = Mailgun::blocking_json(
+            self.primary_account()
+                .blocking_request(Method::POST, subaccounts_url(&self.region, &[]))?
+                .form(&[("name", name)]),
+        )?;
+        Ok(envelope.subaccount)
+    }
+
+    pub fn enable_subaccount(&self, id: &str) -> SendResult<Subaccount> {
+        let envelope: SubaccountEnvelope =
+            Mailgun::blocking_json(self.primary_account().blocking_request(
+                Method::POST,
+                subaccounts_url(&self.region, &[id, ENABLE_ENDPOINT]),
+            )?)?;
+        Ok(envelope.subaccount)
+    }
+
+    /// Stops the subaccount from sending until it is enabled again.
+    pub fn disable_subaccount(&self, id: &str) -> SendResult<Subaccount> {
+        let envelope: SubaccountEnvelope =
+            Mailgun::blocking_json(self.primary_account().blocking_request(
+                Method::POST,
+                subaccounts_url(&self.region, &[id, DISABLE_ENDPOINT]),
+            )?)?;
+        Ok(envelope.subaccount)
+    }
+
+    pub async fn async_list_subaccounts(
+        &self,
+        skip: usize,
+        limit: usize,
+        enabled: Option<bool>,
+    ) -> SendResult<SubaccountList> {
+        Mailgun::async_json(
+            self.primary_account()
+                .async_request(Method::GET, subaccounts_url(&self.region, &[]))?
+                .query(&[("skip", skip), ("limit", limit)])
+                .query(&optional_query("enabled", enabled)),
+        )
+        .await
+    }
+
+    pub async fn async_get_subaccount(&self, id: &str) -> SendResult<Subaccount> {
+        let envelope: SubaccountEnvelope = Mailgun::async_json(
+            self.primary_account()
+                .async_request(Method::GET, subaccounts_url(&self.region, &[id]))?,
+        )
+        .await?;
+        Ok(envelope.subaccount)
+    }
+
+    pub async fn async_create_subaccount(&self, name: &str) -> SendResult<Subaccount> {
+        let envelope: SubaccountEnvelope = Mailgun::async_json(
+            self.primary_account()
+                .async_request(Method::POST, subaccounts_url(&self.region, &[]))?
+                .form(&[("name", name)]),
+        )
+        .await?;
+        Ok(envelope.subaccount)
+    }
+
+    pub async fn async_enable_subaccount(&self, id: &str) -> SendResult<Subaccount> {
+        let envelope: SubaccountEnvelope =
+            Mailgun::async_json(self.primary_account().async_request(
+                Method::POST,
+                subaccounts_url(&self.region, &[id, ENABLE_ENDPOINT]),
+            )?)
+            .await?;
+        Ok(envelope.subaccount)
+    }
+
+    pub async fn async_disable_subaccount(&self, id: &str) -> SendResult<Subaccount> {
+        let envelope: SubaccountEnvelope =
+            Mailgun::async_json(self.primary_account().async_request(
+                Method::POST,
+                subaccounts_url(&self.region, &[id, DISABLE_ENDPOINT]),
+            )?)
+            .await?;
+        Ok(envelope.subaccount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scoped_clients_should_send_on_behalf_of_the_subaccount() {
+        let client = Mailgun {
+            api_key: "key".into(),
+            domain: "example.com".to_string(),
+            ..Default::default()
+        }
+        .on_behalf_of("646d00a1b32c35364a2ad34f");
+
+        let request = client
+            .blocking_request(Method::GET, "https://api.mailgun.net/v3/domains")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(
+            request.headers()["X-Mailgun-On-Behalf-Of"],
+            "646d00a1b32c35364a2ad34f"
+        );
+
+        let request = client
+            .primary_account()
+            .blocking_request(Method::GET, subaccounts_url(&MailgunRegion::US, &[]))
+            .unwrap()
+            .build()
+            .unwrap();
+        assert!(!request.headers().contains_key("X-Mailgun-On-Behalf-Of"));
+    }
+}
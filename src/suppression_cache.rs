@@ -0,0 +1,535 @@
+use crate::{Attachment, EmailAddress, Mailgun, Message, SendError, SendResponse, SendResult};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// What [`SuppressionGuard`] does with recipients found in the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuppressionPolicy {
+    /// Remove suppressed recipients and send to the rest.
+    Drop,
+    /// Refuse to send with [`SendError::Suppressed`].
+    Fail,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    addresses: HashSet<String>,
+    refreshed_at: Option<Instant>,
+    last_error: Option<Arc<SendError>>,
+    /// Refreshes loading the lists right now.
+    loading: usize,
+    /// Inserts (`true`) and removals made while lists were loading, to be
+    /// applied again on top of the loaded addresses.
+    changes: Vec<(Instant, String, bool)>,
+}
+
+/// Local copy of the bounced, globally unsubscribed and complained addresses
+/// of a domain. Addresses are compared case-insensitively.
+#[derive(Debug)]
+pub struct SuppressionCache {
+    ttl: Duration,
+    state: RwLock<CacheState>,
+    /// Set while a [`SuppressionGuard`] refreshes the cache, so concurrent
+    /// sends keep using the current addresses instead of refreshing too.
+    refreshing: AtomicBool,
+}
+
+/// A refresh loading the lists, started at `started`. Stops recording
+/// changes for it when dropped, whether or not it finished.
+struct Loading<'a> {
+    cache: &'a SuppressionCache,
+    started: Instant,
+}
+
+impl Loading<'_> {
+    /// Replaces the cached addresses with `addresses`, keeping the changes
+    /// made since loading started, and returns how many there are now.
+    fn finish(self, mut addresses: HashSet<String>) -> usize {
+        let mut state = self.cache.state.write().unwrap();
+        for (at, address, inserted) in &state.changes {
+            if *at < self.started {
+                continue;
+            }
+            if *inserted {
+                addresses.insert(address.clone());
+            } else {
+                addresses.remove(address);
+            }
+        }
+        state.addresses = addresses;
+        state.refreshed_at = Some(Instant::now());
+        state.last_error = None;
+        state.addresses.len()
+    }
+}
+
+impl Drop for Loading<'_> {
+    fn drop(&mut self) {
+        let mut state = self.cache.state.write().unwrap();
+        state.loading -= 1;
+        if state.loading == 0 {
+            state.changes.clear();
+        }
+    }
+}
+
+/// Marks a guard's refresh as in progress until dropped.
+struct RefreshClaim<'a>(&'a AtomicBool);
+
+impl Drop for RefreshClaim<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+impl SuppressionCache {
+    /// Creates an empty cache that is considered stale `ttl` after each
+    /// refresh.
+    pub fn new(ttl: Duration) -> Self {
+        SuppressionCache {
+            ttl,
+            state: RwLock::new(CacheState::default()),
+            refreshing: AtomicBool::new(false),
+        }
+    }
+
+    pub fn contains(&self, address: &str) -> bool {
+        self.state
+            .read()
+            .unwrap()
+            .addresses
+            .contains(&address.to_lowercase())
+    }
+
+    /// Adds an address, e.g. from a bounce or complaint webhook, without
+    /// waiting for the next refresh. It is kept even if a refresh that is
+    /// loading the lists right now misses it.
+    pub fn insert(&self, address: &str) {
+        self.change(address, true);
+    }
+
+    /// Removes an address, e.g. after deleting its suppression.
+    pub fn remove(&self, address: &str) {
+        self.change(address, false);
+    }
+
+    fn change(&self, address: &str, inserted: bool) {
+        let address = address.to_lowercase();
+        let mut state = self.state.write().unwrap();
+        if state.loading > 0 {
+            state
+                .changes
+                .push((Instant::now(), address.clone(), inserted));
+        }
+        if inserted {
+            state.addresses.insert(address);
+        } else {
+            state.addresses.remove(&address);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.read().unwrap().addresses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether the cache was never refreshed or its TTL has run out.
+    pub fn is_stale(&self) -> bool {
+        match self.state.read().unwrap().refreshed_at {
+            Some(refreshed_at) => refreshed_at.elapsed() >= self.ttl,
+            None => true,
+        }
+    }
+
+    /// When the addresses were last loaded from Mailgun, to tell how stale
+    /// they are after failed refreshes.
+    pub fn refreshed_at(&self) -> Option<Instant> {
+        self.state.read().unwrap().refreshed_at
+    }
+
+    /// Why the last refresh made by a [`SuppressionGuard`] or the background
+    /// refresher failed, until a refresh succeeds.
+    pub fn last_refresh_error(&self) -> Option<Arc<SendError>> {
+        self.state.read().unwrap().last_error.clone()
+    }
+
+    fn record_refresh_error(&self, err: SendError) {
+        self.state.write().unwrap().last_error = Some(Arc::new(err));
+    }
+
+    /// Records a failed refresh so sends can go on with the addresses loaded
+    /// before, or returns the error if there are none.
+    fn keep_stale_addresses(&self, err: SendError) -> SendResult<()> {
+        if self.refreshed_at().is_none() {
+            return Err(err);
+        }
+        self.record_refresh_error(err);
+        Ok(())
+    }
+
+    /// Claims the refresh of a stale cache, or `None` if it is fresh or
+    /// another caller is already refreshing it.
+    fn claim_stale_refresh(&self) -> Option<RefreshClaim<'_>> {
+        if !self.is_stale() {
+            return None;
+        }
+        self.refreshing
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .ok()
+            .map(|_| RefreshClaim(&self.refreshing))
+    }
+
+    fn start_loading(&self) -> Loading<'_> {
+        let mut state = self.state.write().unwrap();
+        state.loading += 1;
+        Loading {
+            cache: self,
+            started: Instant::now(),
+        }
+    }
+
+    /// Reloads every bounce, global unsubscribe and complaint of the client's
+    /// domain, replacing the cached addresses once all lists are fetched.
+    /// Addresses inserted or removed meanwhile stay that way.
+    pub fn refresh(&self, client: &Mailgun) -> SendResult<usize> {
+        let loading = self.start_loading();
+        let mut addresses = HashSet::new();
+
+        let mut bounces = client.list_bounces(Some(1000))?;
+        while !bounces.items.is_empty() {
+            addresses.extend(bounces.items.iter().map(|b| b.address.to_lowercase()));
+            bounces = client.next_page(&bounces)?;
+        }
+
+        let mut unsubscribes = client.list_unsubscribes(Some(1000))?;
+        while !unsubscribes.items.is_empty() {
+            addresses.extend(
+                unsubscribes
+                    .items
+                    .iter()
+                    .filter(|u| u.is_global())
+                    .map(|u| u.address.to_lowercase()),
+            );
+            unsubscribes = client.next_page(&unsubscribes)?;
+        }
+
+        let mut complaints = client.list_complaints(Some(1000))?;
+        while !complaints.items.is_empty() {
+            addresses.extend(complaints.items.iter().map(|c| c.address.to_lowercase()));
+            complaints = client.next_page(&complaints)?;
+        }
+
+        Ok(loading.finish(addresses))
+    }
+
+    pub async fn async_refresh(&self, client: &Mailgun) -> SendResult<usize> {
+        let loading = self.start_loading();
+        let mut addresses = HashSet::new();
+
+        let mut bounces = client.async_list_bounces(Some(1000)).await?;
+        while !bounces.items.is_empty() {
+            addresses.extend(bounces.items.iter().map(|b| b.address.to_lowercase()));
+            bounces = client.async_next_page(&bounces).await?;
+        }
+
+        let mut unsubscribes = client.async_list_unsubscribes(Some(1000)).await?;
+        while !unsubscribes.items.is_empty() {
+            addresses.extend(
+                unsubscribes
+                    .items
+                    .iter()
+                    .filter(|u| u.is_global())
+                    .map(|u| u.address.to_lowercase()),
+            );
+            unsubscribes = client.async_next_page(&unsubscribes).await?;
+        }
+
+        let mut complaints = client.async_list_complaints(Some(1000)).await?;
+        while !complaints.items.is_empty() {
+            addresses.extend(complaints.items.iter().map(|c| c.address.to_lowercase()));
+            complaints = client.async_next_page(&complaints).await?;
+        }
+
+        Ok(loading.finish(addresses))
+    }
+
+    /// Refreshes the cache on a background thread every `ttl` until the
+    /// returned handle is dropped. Failed refreshes are retried on the next
+    /// tick; until then the previous addresses stay in place and the error is
+    /// kept in [`SuppressionCache::last_refresh_error`].
+    pub fn spawn_refresher(self: &Arc<Self>, client: Mailgun) -> RefresherHandle {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let cache = Arc::clone(self);
+        thread::spawn(move || loop {
+            if let Err(err) = cache.refresh(&client) {
+                cache.record_refresh_error(err);
+            }
+            match stopped.recv_timeout(cache.ttl) {
+                Err(RecvTimeoutError::Timeout) => continue,
+                _ => break,
+            }
+        });
+        RefresherHandle { _stop: stop }
+    }
+
+    /// Removes suppressed recipients from `message`, or fails, depending on
+    /// `policy`. Sending with no `to` recipients left fails too.
+    fn filter_message(&self, message: Message, policy: SuppressionPolicy) -> SendResult<Message> {
+        let mut suppressed = Vec::new();
+        let mut keep = |addresses: Vec<EmailAddress>| {
+            addresses
+                .into_iter()
+                .filter(|address| {
+                    let hit = self.contains(&address.address);
+                    if hit {
+                        suppressed.push(address.address.clone());
+                    }
+                    !hit
+                })
+                .collect::<Vec<EmailAddress>>()
+        };
+
+        let to = keep(message.to);
+        let cc = keep(message.cc);
+        let bcc = keep(message.bcc);
+
+        if !suppressed.is_empty() && (policy == SuppressionPolicy::Fail || to.is_empty()) {
+            return Err(SendError::Suppressed {
+                addresses: suppressed,
+            });
+        }
+
+        Ok(Message {
+            to,
+            cc,
+            bcc,
+            ..message
+        })
+    }
+}
+
+/// Stops the background refresher of a [`SuppressionCache`] when dropped.
+#[derive(Debug)]
+pub struct RefresherHandle {
+    _stop: Sender<()>,
+}
+
+/// A [`Mailgun`] client that checks recipients against a
+/// [`SuppressionCache`] before sending.
+///
+/// A stale cache is refreshed by the first send that notices, while
+/// concurrent sends go on with the current addresses. If that refresh fails,
+/// the send goes on with the addresses of the last successful refresh and
+/// the error is kept in [`SuppressionCache::last_refresh_error`]; only a
+/// cache that was never loaded fails the send. Refresh the cache before the
+/// first send, or keep it fresh with [`SuppressionCache::spawn_refresher`]
+/// so no send waits on a refresh.
+#[derive(Debug, Clone)]
+pub struct SuppressionGuard {
+    client: Mailgun,
+    cache: Arc<SuppressionCache>,
+    policy: SuppressionPolicy,
+}
+
+impl Mailgun {
+    pub fn with_suppression_guard(
+        self,
+        cache: Arc<SuppressionCache>,
+        policy: SuppressionPolicy,
+    ) -> SuppressionGuard {
+        SuppressionGuard {
+            client: self,
+            cache,
+            policy,
+        }
+    }
+}
+
+impl SuppressionGuard {
+    pub fn client(&self) -> &Mailgun {
+        &self.client
+    }
+
+    pub fn cache(&self) -> &Arc<SuppressionCache> {
+        &self.cache
+    }
+
+    pub fn send(
+        &self,
+        sender: &EmailAddress,
+        message: Message,
+        attachments: Option<Vec<Attachment>>,
+    ) -> SendResult<SendResponse> {
+        if let Some(_claim) = self.cache.claim_stale_refresh() {
+            if let Err(err) = self.cache.refresh(&self.client) {
+                self.cache.keep_stale_addresses(err)?;
+            }
+        }
+        let message = self.cache.filter_message(message, self.policy)?;
+        self.client.send(sender, message, attachments)
+    }
+
+    pub async fn async_send(
+        &self,
+        sender: &EmailAddress,
+        message: Message,
+        attachments: Option<Vec<Attachment>>,
+    ) -> SendResult<SendResponse> {
+        if let Some(_claim) = self.cache.claim_stale_refresh() {
+            if let Err(err) = self.cache.async_refresh(&self.client).await {
+                self.cache.keep_stale_addresses(err)?;
+            }
+        }
+        let message = self.cache.filter_message(message, self.policy)?;
+        self.client.async_send(sender, message, attachments).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_message_should_follow_policy() {
+        let cache = SuppressionCache::new(Duration::from_secs(60));
+        cache.insert("Bounced@Example.com");
+        let message = Message::builder()
+            .to(vec!["ok@example.com".into(), "bounced@example.com".into()])
+            .bcc(vec!["BOUNCED@example.com".into()])
+            .subject("Hello")
+            .build();
+
+        let filtered = cache
+            .filter_message(message.clone(), SuppressionPolicy::Drop)
+            .unwrap();
+        assert_eq!(filtered.to, vec![EmailAddress::address("ok@example.com")]);
+        assert!(filtered.bcc.is_empty());
+
+        match cache.filter_message(message, SuppressionPolicy::Fail) {
+            Err(SendError::Suppressed { addresses }) => assert_eq!(
+                addresses,
+                vec!["bounced@example.com", "BOUNCED@example.com"]
+            ),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn stale_cache_should_be_refreshed_by_one_sender_at_a_time() {
+        let cache = Arc::new(SuppressionCache::new(Duration::from_secs(60)));
+        cache.insert("bounced@example.com");
+        // Nothing listens on port 1, so a refresh fails without the network.
+        let guard = Mailgun {
+            region: "http://127.0.0.1:1".parse().unwrap(),
+            ..Default::default()
+        }
+        .with_suppression_guard(Arc::clone(&cache), SuppressionPolicy::Fail);
+        let sender = EmailAddress::address("sender@example.com");
+        let message = Message::builder()
+            .to(vec!["bounced@example.com".into()])
+            .subject("Hello")
+            .build();
+
+        match guard.send(&sender, message.clone(), None) {
+            Err(SendError::Req(_)) => {}
+            other => panic!("stale cache was not refreshed: {:?}", other),
+        }
+        assert!(cache.is_stale());
+
+        let in_progress = cache.claim_stale_refresh().unwrap();
+        assert!(cache.claim_stale_refresh().is_none());
+        match guard.send(&sender, message, None) {
+            Err(SendError::Suppressed { addresses }) => {
+                assert_eq!(addresses, vec!["bounced@example.com"])
+            }
+            other => panic!("send waited on a second refresh: {:?}", other),
+        }
+        drop(in_progress);
+        assert!(cache.claim_stale_refresh().is_some());
+    }
+
+    #[test]
+    fn failed_refresh_should_keep_serving_the_last_addresses() {
+        let cache = Arc::new(SuppressionCache::new(Duration::ZERO));
+        cache.start_loading().finish(
+            ["bounced@example.com".to_string()]
+                .iter()
+                .cloned()
+                .collect(),
+        );
+        let guard = Mailgun {
+            region: "http://127.0.0.1:1".parse().unwrap(),
+            ..Default::default()
+        }
+        .with_suppression_guard(Arc::clone(&cache), SuppressionPolicy::Fail);
+        let message = Message::builder()
+            .to(vec!["bounced@example.com".into()])
+            .subject("Hello")
+            .build();
+
+        for _ in 0..2 {
+            match guard.send(
+                &EmailAddress::address("sender@example.com"),
+                message.clone(),
+                None,
+            ) {
+                Err(SendError::Suppressed { addresses }) => {
+                    assert_eq!(addresses, vec!["bounced@example.com"])
+                }
+                other => panic!("send did not use the last addresses: {:?}", other),
+            }
+        }
+        match cache.last_refresh_error().as_deref() {
+            Some(SendError::Req(_)) => {}
+            other => panic!("failed refresh was not recorded: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn background_refresher_should_record_failures() {
+        let cache = Arc::new(SuppressionCache::new(Duration::from_secs(60)));
+        let _refresher = cache.spawn_refresher(Mailgun {
+            region: "http://127.0.0.1:1".parse().unwrap(),
+            ..Default::default()
+        });
+
+        let started = Instant::now();
+        while cache.last_refresh_error().is_none() {
+            assert!(
+                started.elapsed() < Duration::from_secs(10),
+                "no refresh error"
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(cache.refreshed_at().is_none());
+    }
+
+    #[test]
+    fn changes_during_a_refresh_should_survive_it() {
+        let cache = SuppressionCache::new(Duration::from_secs(60));
+        cache.insert("removed@example.com");
+        cache.insert("before@example.com");
+
+        let loading = cache.start_loading();
+        cache.insert("webhook@example.com");
+        cache.remove("removed@example.com");
+        let loaded = ["listed@example.com", "removed@example.com"]
+            .iter()
+            .map(|address| address.to_string())
+            .collect();
+        assert_eq!(loading.finish(loaded), 2);
+
+        assert!(cache.contains("listed@example.com"));
+        assert!(cache.contains("webhook@example.com"));
+        assert!(!cache.contains("removed@example.com"));
+        assert!(!cache.contains("before@example.com"));
+        assert!(cache.state.read().unwrap().changes.is_empty());
+    }
+}
@@ -0,0 +1,827 @@
+use crate::{
+    async_file_part, blocking_file_part, build_url, get_base_url, limit_query, Mailgun, Page,
+    SendResult,
+};
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::io::Write;
+use typed_builder::TypedBuilder;
+
+const BOUNCES_ENDPOINT: &str = "bounces";
+const UNSUBSCRIBES_ENDPOINT: &str = "unsubscribes";
+const COMPLAINTS_ENDPOINT: &str = "complaints";
+const WHITELISTS_ENDPOINT: &str = "whitelists";
+const IMPORT_ENDPOINT: &str = "import";
+
+/// Largest page size the suppression list endpoints accept.
+const EXPORT_PAGE_LIMIT: usize = 1000;
+
+/// The suppression lists of a domain.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SuppressionKind {
+    Bounces,
+    Unsubscribes,
+    Complaints,
+    Allowlist,
+}
+
+impl SuppressionKind {
+    fn endpoint(self) -> &'static str {
+        match self {
+            SuppressionKind::Bounces => BOUNCES_ENDPOINT,
+            SuppressionKind::Unsubscribes => UNSUBSCRIBES_ENDPOINT,
+            SuppressionKind::Complaints => COMPLAINTS_ENDPOINT,
+            SuppressionKind::Allowlist => WHITELISTS_ENDPOINT,
+        }
+    }
+}
+
+/// A CSV file to import, either on disk or already in memory. Files are
+/// streamed rather than read up front.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum CsvSource {
+    Path(String),
+    Bytes(Vec<u8>),
+}
+
+impl CsvSource {
+    pub(crate) fn blocking_form(
+        self,
+        field_name: &'static str,
+    ) -> SendResult<reqwest::blocking::multipart::Form> {
+        let form = reqwest::blocking::multipart::Form::new();
+        match self {
+            CsvSource::Path(path) => blocking_file_part(form, field_name, &path),
+            CsvSource::Bytes(bytes) => Ok(form.part(
+                field_name,
+                reqwest::blocking::multipart::Part::bytes(bytes).file_name("upload.csv"),
+            )),
+        }
+    }
+
+    pub(crate) async fn async_form(
+        self,
+        field_name: &'static str,
+    ) -> SendResult<reqwest::multipart::Form> {
+        let form = reqwest::multipart::Form::new();
+        match self {
+            CsvSource::Path(path) => async_file_part(form, field_name, &path).await,
+            CsvSource::Bytes(bytes) => Ok(form.part(
+                field_name,
+                reqwest::multipart::Part::bytes(bytes).file_name("upload.csv"),
+            )),
+        }
+    }
+}
+
+/// A suppression that can be written as a row of the CSV Mailgun imports.
+trait CsvRecord: DeserializeOwned {
+    const HEADER: &'static [&'static str];
+
+    fn csv_fields(&self) -> Vec<String>;
+}
+
+fn csv_row(fields: &[String]) -> String {
+    let mut row = fields
+        .iter()
+        .map(|field| {
+            if field.contains([',', '"', '\r', '\n']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(",");
+    row.push('\n');
+    row
+}
+
+fn csv_header<T: CsvRecord>() -> String {
+    csv_row(&T::HEADER.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+}
+
+/// Mailgun reports bounce codes either as strings or as numbers.
+fn string_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(value) => Ok(value),
+        value => Ok(value.to_string()),
+    }
+}
+
+/// An address that hard-bounced, so Mailgun no longer delivers to it.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Bounce {
+    pub address: String,
+    #[serde(deserialize_with = "string_or_number")]
+    pub code: String,
+    #[serde(default)]
+    pub error: String,
+    pub created_at: String,
+}
+
+impl CsvRecord for Bounce {
+    const HEADER: &'static [&'static str] = &["address", "code", "error", "created_at"];
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.address.clone(),
+            self.code.clone(),
+            self.error.clone(),
+            self.created_at.clone(),
+        ]
+    }
+}
+
+/// Tag Mailgun uses for an address unsubscribed from all mail of a domain.
+pub const ALL_TAGS: &str = "*";
+
+/// An address that no longer receives mail from the domain, or from some of
+/// its tags.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Unsubscribe {
+    pub address: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub created_at: String,
+}
+
+impl Unsubscribe {
+    /// Whether the address is unsubscribed from every tag of the domain.
+    pub fn is_global(&self) -> bool {
+        self.tags.is_empty() || self.tags.iter().any(|tag| tag == ALL_TAGS)
+    }
+}
+
+/// An unsubscribe to add. Leaving `tags` empty unsubscribes the address from
+/// all mail of the domain.
+#[derive(TypedBuilder, Serialize, Debug, PartialEq, Eq, Clone)]
+pub struct NewUnsubscribe {
+    #[builder(setter(into))]
+    pub address: String,
+    #[builder(default, setter(into))]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[builder(default, setter(strip_option, into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+}
+
+impl NewUnsubscribe {
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![("address", self.address.clone())];
+        for tag in &self.tags {
+            params.push(("tag", tag.clone()));
+        }
+        if let Some(ref created_at) = self.created_at {
+            params.push(("created_at", created_at.clone()));
+        }
+        params
+    }
+}
+
+impl CsvRecord for Unsubscribe {
+    const HEADER: &'static [&'static str] = &["address", "tags", "created_at"];
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.address.clone(),
+            self.tags.join(","),
+            self.created_at.clone(),
+        ]
+    }
+}
+
+impl From<&str> for NewUnsubscribe {
+    fn from(address: &str) -> Self {
+        NewUnsubscribe {
+            address: address.to_string(),
+            tags: vec![],
+            created_at: None,
+        }
+    }
+}
+
+/// An address that reported mail from the domain as spam.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Complaint {
+    pub address: String,
+    pub created_at: String,
+}
+
+impl CsvRecord for Complaint {
+    const HEADER: &'static [&'static str] = &["address", "created_at"];
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![self.address.clone(), self.created_at.clone()]
+    }
+}
+
+#[derive(TypedBuilder, Serialize, Debug, PartialEq, Eq, Clone)]
+pub struct NewComplaint {
+    #[builder(setter(into))]
+    pub address: String,
+    #[builder(default, setter(strip_option, into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+}
+
+impl NewComplaint {
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![("address", self.address.clone())];
+        if let Some(ref created_at) = self.created_at {
+            params.push(("created_at", created_at.clone()));
+        }
+        params
+    }
+}
+
+impl From<&str> for NewComplaint {
+    fn from(address: &str) -> Self {
+        NewComplaint {
+            address: address.to_string(),
+            created_at: None,
+        }
+    }
+}
+
+/// Whether an allowlist entry covers a single address or a whole domain.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum AllowlistKind {
+    Address,
+    Domain,
+}
+
+impl AllowlistKind {
+    fn field(self) -> &'static str {
+        match self {
+            AllowlistKind::Address => "address",
+            AllowlistKind::Domain => "domain",
+        }
+    }
+}
+
+/// An address or domain that is never suppressed, served by Mailgun's
+/// `whitelists` endpoint.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct AllowlistEntry {
+    pub value: String,
+    #[serde(default)]
+    pub reason: String,
+    #[serde(rename = "type")]
+    pub kind: AllowlistKind,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+impl CsvRecord for AllowlistEntry {
+    const HEADER: &'static [&'static str] = &["address", "domain", "reason", "created_at"];
+
+    fn csv_fields(&self) -> Vec<String> {
+        let (address, domain) = match self.kind {
+            AllowlistKind::Address => (self.value.clone(), String::new()),
+            AllowlistKind::Domain => (String::new(), self.value.clone()),
+        };
+        vec![
+            address,
+            domain,
+            self.reason.clone(),
+            self.created_at.clone(),
+        ]
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct NewAllowlistEntry {
+    pub kind: AllowlistKind,
+    pub value: String,
+    pub reason: Option<String>,
+}
+
+impl NewAllowlistEntry {
+    pub fn address(address: &str) -> Self {
+        NewAllowlistEntry {
+            kind: AllowlistKind::Address,
+            value: address.to_string(),
+            reason: None,
+        }
+    }
+
+    pub fn domain(domain: &str) -> Self {
+        NewAllowlistEntry {
+            kind: AllowlistKind::Domain,
+            value: domain.to_string(),
+            reason: None,
+        }
+    }
+
+    pub fn reason(mut self, reason: &str) -> Self {
+        self.reason = Some(reason.to_string());
+        self
+    }
+
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![(self.kind.field(), self.value.clone())];
+        if let Some(ref reason) = self.reason {
+            params.push(("reason", reason.clone()));
+        }
+        params
+    }
+}
+
+/// Builds the `address,domain` CSV the allowlist import endpoint expects.
+fn allowlist_csv(entries: &[NewAllowlistEntry]) -> String {
+    let mut csv = csv_row(&["address".to_string(), "domain".to_string()]);
+    for entry in entries {
+        let row = match entry.kind {
+            AllowlistKind::Address => [entry.value.clone(), String::new()],
+            AllowlistKind::Domain => [String::new(), entry.value.clone()],
+        };
+        csv.push_str(&csv_row(&row));
+    }
+    csv
+}
+
+/// Response to suppression writes. Bulk uploads only carry `message`.
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct SuppressionResponse {
+    pub message: String,
+    #[serde(default, alias = "value")]
+    pub address: Option<String>,
+}
+
+impl Mailgun {
+    fn suppressions_url(&self, endpoint: &str, address: Option<&str>) -> reqwest::Url {
+        let mut segments = vec![self.domain.as_str(), endpoint];
+        segments.extend(address);
+        build_url(get_base_url(&self.region), &segments)
+    }
+
+    fn bounces_url(&self, address: Option<&str>) -> reqwest::Url {
+        self.suppressions_url(BOUNCES_ENDPOINT, address)
+    }
+
+    fn unsubscribes_url(&self, address: Option<&str>) -> reqwest::Url {
+        self.suppressions_url(UNSUBSCRIBES_ENDPOINT, address)
+    }
+
+    fn complaints_url(&self, address: Option<&str>) -> reqwest::Url {
+        self.suppressions_url(COMPLAINTS_ENDPOINT, address)
+    }
+
+    fn allowlist_url(&self, value: Option<&str>) -> reqwest::Url {
+        self.suppressions_url(WHITELISTS_ENDPOINT, value)
+    }
+
+    fn blocking_export<T: CsvRecord>(
+        &self,
+        url: reqwest::Url,
+        mut writer: impl Write,
+    ) -> SendResult<usize> {
+        writer.write_all(csv_header::<T>().as_bytes())?;
+        let mut count = 0;
+        let mut page: Page<T> = Mailgun::blocking_json(
+            self.blocking_request(Method::GET, url)?
+                .query(&limit_query(Some(EXPORT_PAGE_LIMIT))),
+        )?;
+        while !page.items.is_empty() {
+            for item in &page.items {
+                writer.write_all(csv_row(&item.csv_fields()).as_bytes())?;
+            }
+            count += page.items.len();
+            page = self.next_page(&page)?;
+        }
+        writer.flush()?;
+        Ok(count)
+    }
+
+    async fn async_export<T: CsvRecord>(&self, url: reqwest::Url) -> SendResult<String> {
+        let mut csv = csv_header::<T>();
+        let mut page: Page<T> = Mailgun::async_json(
+            self.async_request(Method::GET, url)?
+                .query(&limit_query(Some(EXPORT_PAGE_LIMIT))),
+        )
+        .await?;
+        while !page.items.is_empty() {
+            for item in &page.items {
+                csv.push_str(&csv_row(&item.csv_fields()));
+            }
+            page = self.async_next_page(&page).await?;
+        }
+        Ok(csv)
+    }
+
+    /// Lists bounces, as needed to export them or load a suppression cache.
+    pub fn list_bounces(&self, limit: Option<usize>) -> SendResult<Page<Bounce>> {
+        let url = self.bounces_url(None);
+        Mailgun::blocking_json(
+            self.blocking_request(Method::GET, url)?
+                .query(&limit_query(limit)),
+        )
+    }
+
+    /// Uploads a CSV of suppressions in the format Mailgun documents for
+    /// `kind`, e.g. one written by [`Mailgun::export_suppressions`].
+    pub fn import_suppressions(
+        &self,
+        kind: SuppressionKind,
+        csv: CsvSource,
+    ) -> SendResult<SuppressionResponse> {
+        let url = build_url(
+            get_base_url(&self.region),
+            &[&self.domain, kind.endpoint(), IMPORT_ENDPOINT],
+        );
+        let form = csv.blocking_form("file")?;
+        Mailgun::blocking_json(self.blocking_request(Method::POST, url)?.multipart(form))
+    }
+
+    /// Writes every suppression of `kind` to `writer` as CSV, one page at a
+    /// time, and returns how many rows were written.
+    pub fn export_suppressions(
+        &self,
+        kind: SuppressionKind,
+        writer: impl Write,
+    ) -> SendResult<usize> {
+        let url = self.suppressions_url(kind.endpoint(), None);
+        match kind {
+            SuppressionKind::Bounces => self.blocking_export::<Bounce>(url, writer),
+            SuppressionKind::Unsubscribes => self.blocking_export::<Unsubscribe>(url, writer),
+            SuppressionKind::Complaints => self.blocking_export::<Complaint>(url, writer),
+            SuppressionKind::Allowlist => self.blocking_export::<AllowlistEntry>(url, writer),
+        }
+    }
+
+    /// Lists unsubscribed addresses, `limit` per page (Mailgun allows up to
+    /// 1000). Use [`Mailgun::next_page`] to walk the rest.
+    pub fn list_unsubscribes(&self, limit: Option<usize>) -> SendResult<Page<Unsubscribe>> {
+        let url = self.unsubscribes_url(None);
+        Mailgun::blocking_json(
+            self.blocking_request(Method::GET, url)?
+                .query(&limit_query(limit)),
+        )
+    }
+
+    pub fn get_unsubscribe(&self, address: &str) -> SendResult<Unsubscribe> {
+        let url = self.unsubscribes_url(Some(address));
+        Mailgun::blocking_json(self.blocking_request(Method::GET, url)?)
+    }
+
+    pub fn add_unsubscribe(&self, unsubscribe: &NewUnsubscribe) -> SendResult<SuppressionResponse> {
+        let url = self.unsubscribes_url(None);
+        Mailgun::blocking_json(
+            self.blocking_request(Method::POST, url)?
+                .form(&unsubscribe.params()),
+        )
+    }
+
+    /// Adds up to 1000 unsubscribes in a single JSON upload.
+    pub fn add_unsubscribes(
+        &self,
+        unsubscribes: &[NewUnsubscribe],
+    ) -> SendResult<SuppressionResponse> {
+        let url = self.unsubscribes_url(None);
+        Mailgun::blocking_json(self.blocking_request(Method::POST, url)?.json(unsubscribes))
+    }
+
+    /// Removes the address from the unsubscribe list entirely.
+    pub fn delete_unsubscribe(&self, address: &str) -> SendResult<SuppressionResponse> {
+        let url = self.unsubscribes_url(Some(address));
+        Mailgun::blocking_json(self.blocking_request(Method::DELETE, url)?)
+    }
+
+    /// Resubscribes the address to `tag` only, keeping its other unsubscribes.
+    pub fn delete_unsubscribe_tag(
+        &self,
+        address: &str,
+        tag: &str,
+    ) -> SendResult<SuppressionResponse> {
+        let url = self.unsubscribes_url(Some(address));
+        Mailgun::blocking_json(
+            self.blocking_request(Method::DELETE, url)?
+                .query(&[("tag", tag)]),
+        )
+    }
+
+    pub fn list_complaints(&self, limit: Option<usize>) -> SendResult<Page<Complaint>> {
+        let url = self.complaints_url(None);
+        Mailgun::blocking_json(
+            self.blocking_request(Method::GET, url)?
+                .query(&limit_query(limit)),
+        )
+    }
+
+    pub fn get_complaint(&self, address: &str) -> SendResult<Complaint> {
+        let url = self.complaints_url(Some(address));
+        Mailgun::blocking_json(self.blocking_request(Method::GET, url)?)
+    }
+
+    pub fn add_complaint(&self, complaint: &NewComplaint) -> SendResult<SuppressionResponse> {
+        let url = self.complaints_url(None);
+        Mailgun::blocking_json(
+            self.blocking_request(Method::POST, url)?
+                .form(&complaint.params()),
+        )
+    }
+
+    /// Adds up to 1000 complaints in a single JSON upload.
+    pub fn add_complaints(&self, complaints: &[NewComplaint]) -> SendResult<SuppressionResponse> {
+        let url = self.complaints_url(None);
+        Mailgun::blocking_json(self.blocking_request(Method::POST, url)?.json(complaints))
+    }
+
+    pub fn delete_complaint(&self, address: &str) -> SendResult<SuppressionResponse> {
+        let url = self.complaints_url(Some(address));
+        Mailgun::blocking_json(self.blocking_request(Method::DELETE, url)?)
+    }
+
+    pub fn list_allowlist(&self, limit: Option<usize>) -> SendResult<Page<AllowlistEntry>> {
+        let url = self.allowlist_url(None);
+        Mailgun::blocking_json(
+            self.blocking_request(Method::GET, url)?
+                .query(&limit_query(limit)),
+        )
+    }
+
+    /// Looks up an allowlisted address or domain.
+    pub fn get_allowlist_entry(&self, value: &str) -> SendResult<AllowlistEntry> {
+        let url = self.allowlist_url(Some(value));
+        Mailgun::blocking_json(self.blocking_request(Method::GET, url)?)
+    }
+
+    pub fn add_allowlist_entry(
+        &self,
+        entry: &NewAllowlistEntry,
+    ) -> SendResult<SuppressionResponse> {
+        let url = self.allowlist_url(None);
+        Mailgun::blocking_json(
+            self.blocking_request(Method::POST, url)?
+                .form(&entry.params()),
+        )
+    }
+
+    /// Allowlists many addresses and domains at once. Mailgun only takes bulk
+    /// allowlist entries as a CSV upload, so reasons are not sent.
+    pub fn import_allowlist(
+        &self,
+        entries: &[NewAllowlistEntry],
+    ) -> SendResult<SuppressionResponse> {
+        let csv = CsvSource::Bytes(allowlist_csv(entries).into_bytes());
+        self.import_suppressions(SuppressionKind::Allowlist, csv)
+    }
+
+    pub fn delete_allowlist_entry(&self, value: &str) -> SendResult<SuppressionResponse> {
+        let url = self.allowlist_url(Some(value));
+        Mailgun::blocking_json(self.blocking_request(Method::DELETE, url)?)
+    }
+
+    pub async fn async_list_bounces(&self, limit: Option<usize>) -> SendResult<Page<Bounce>> {
+        let url = self.bounces_url(None);
+        Mailgun::async_json(
+            self.async_request(Method::GET, url)?
+                .query(&limit_query(limit)),
+        )
+        .await
+    }
+
+    pub async fn async_import_suppressions(
+        &self,
+        kind: SuppressionKind,
+        csv: CsvSource,
+    ) -> SendResult<SuppressionResponse> {
+        let url = build_url(
+            get_base_url(&self.region),
+            &[&self.domain, kind.endpoint(), IMPORT_ENDPOINT],
+        );
+        let form = csv.async_form("file").await?;
+        Mailgun::async_json(self.async_request(Method::POST, url)?.multipart(form)).await
+    }
+
+    /// Like [`Mailgun::export_suppressions`], but returns the CSV for the
+    /// caller to write with its runtime's async IO, as writing to a blocking
+    /// writer would stall the executor.
+    pub async fn async_export_suppressions(&self, kind: SuppressionKind) -> SendResult<String> {
+        let url = self.suppressions_url(kind.endpoint(), None);
+        match kind {
+            SuppressionKind::Bounces => self.async_export::<Bounce>(url).await,
+            SuppressionKind::Unsubscribes => self.async_export::<Unsubscribe>(url).await,
+            SuppressionKind::Complaints => self.async_export::<Complaint>(url).await,
+            SuppressionKind::Allowlist => self.async_export::<AllowlistEntry>(url).await,
+        }
+    }
+
+    pub async fn async_list_unsubscribes(
+        &self,
+        limit: Option<usize>,
+    ) -> SendResult<Page<Unsubscribe>> {
+        let url = self.unsubscribes_url(None);
+        Mailgun::async_json(
+            self.async_request(Method::GET, url)?
+                .query(&limit_query(limit)),
+        )
+        .await
+    }
+
+    pub async fn async_get_unsubscribe(&self, address: &str) -> SendResult<Unsubscribe> {
+        let url = self.unsubscribes_url(Some(address));
+        Mailgun::async_json(self.async_request(Method::GET, url)?).await
+    }
+
+    pub async fn async_add_unsubscribe(
+        &self,
+        unsubscribe: &NewUnsubscribe,
+    ) -> SendResult<SuppressionResponse> {
+        let url = self.unsubscribes_url(None);
+        Mailgun::async_json(
+            self.async_request(Method::POST, url)?
+                .form(&unsubscribe.params()),
+        )
+        .await
+    }
+
+    pub async fn async_add_unsubscribes(
+        &self,
+        unsubscribes: &[NewUnsubscribe],
+    ) -> SendResult<SuppressionResponse> {
+        let url = self.unsubscribes_url(None);
+        Mailgun::async_json(self.async_request(Method::POST, url)?.json(unsubscribes)).await
+    }
+
+    pub async fn async_delete_unsubscribe(&self, address: &str) -> SendResult<SuppressionResponse> {
+        let url = self.unsubscribes_url(Some(address));
+        Mailgun::async_json(self.async_request(Method::DELETE, url)?).await
+    }
+
+    pub async fn async_delete_unsubscribe_tag(
+        &self,
+        address: &str,
+        tag: &str,
+    ) -> SendResult<SuppressionResponse> {
+        let url = self.unsubscribes_url(Some(address));
+        Mailgun::async_json(
+            self.async_request(Method::DELETE, url)?
+                .query(&[("tag", tag)]),
+        )
+        .await
+    }
+
+    pub async fn async_list_complaints(&self, limit: Option<usize>) -> SendResult<Page<Complaint>> {
+        let url = self.complaints_url(None);
+        Mailgun::async_json(
+            self.async_request(Method::GET, url)?
+                .query(&limit_query(limit)),
+        )
+        .await
+    }
+
+    pub async fn async_get_complaint(&self, address: &str) -> SendResult<Complaint> {
+        let url = self.complaints_url(Some(address));
+        Mailgun::async_json(self.async_request(Method::GET, url)?).await
+    }
+
+    pub async fn async_add_complaint(
+        &self,
+        complaint: &NewComplaint,
+    ) -> SendResult<SuppressionResponse> {
+        let url = self.complaints_url(None);
+        Mailgun::async_json(
+            self.async_request(Method::POST, url)?
+                .form(&complaint.params()),
+        )
+        .await
+    }
+
+    pub async fn async_add_complaints(
+        &self,
+        complaints: &[NewComplaint],
+    ) -> SendResult<SuppressionResponse> {
+        let url = self.complaints_url(None);
+        Mailgun::async_json(self.async_request(Method::POST, url)?.json(complaints)).await
+    }
+
+    pub async fn async_delete_complaint(&self, address: &str) -> SendResult<SuppressionResponse> {
+        let url = self.complaints_url(Some(address));
+        Mailgun::async_json(self.async_request(Method::DELETE, url)?).await
+    }
+
+    pub async fn async_list_allowlist(
+        &self,
+        limit: Option<usize>,
+    ) -> SendResult<Page<AllowlistEntry>> {
+        let url = self.allowlist_url(None);
+        Mailgun::async_json(
+            self.async_request(Method::GET, url)?
+                .query(&limit_query(limit)),
+        )
+        .await
+    }
+
+    pub async fn async_get_allowlist_entry(&self, value: &str) -> SendResult<AllowlistEntry> {
+        let url = self.allowlist_url(Some(value));
+        Mailgun::async_json(self.async_request(Method::GET, url)?).await
+    }
+
+    pub async fn async_add_allowlist_entry(
+        &self,
+        entry: &NewAllowlistEntry,
+    ) -> SendResult<SuppressionResponse> {
+        let url = self.allowlist_url(None);
+        Mailgun::async_json(self.async_request(Method::POST, url)?.form(&entry.params())).await
+    }
+
+    pub async fn async_import_allowlist(
+        &self,
+        entries: &[NewAllowlistEntry],
+    ) -> SendResult<SuppressionResponse> {
+        let csv = CsvSource::Bytes(allowlist_csv(entries).into_bytes());
+        self.async_import_suppressions(SuppressionKind::Allowlist, csv)
+            .await
+    }
+
+    pub async fn async_delete_allowlist_entry(
+        &self,
+        value: &str,
+    ) -> SendResult<SuppressionResponse> {
+        let url = self.allowlist_url(Some(value));
+        Mailgun::async_json(self.async_request(Method::DELETE, url)?).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MailgunRegion;
+
+    #[test]
+    fn unsubscribe_params_and_json_should_carry_tags() {
+        let unsubscribe = NewUnsubscribe::builder()
+            .address("jane@example.com")
+            .tags(vec!["newsletter".to_string(), "digest".to_string()])
+            .build();
+        assert_eq!(
+            unsubscribe.params(),
+            vec![
+                ("address", "jane@example.com".to_string()),
+                ("tag", "newsletter".to_string()),
+                ("tag", "digest".to_string()),
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(vec![unsubscribe, "john@example.com".into()]).unwrap(),
+            serde_json::json!([
+                {"address": "jane@example.com", "tags": ["newsletter", "digest"]},
+                {"address": "john@example.com"},
+            ])
+        );
+    }
+
+    #[test]
+    fn allowlist_csv_should_split_addresses_and_domains() {
+        let entries = vec![
+            NewAllowlistEntry::address("qa@example.com").reason("QA inbox"),
+            NewAllowlistEntry::domain("test.example.com"),
+        ];
+        assert_eq!(
+            allowlist_csv(&entries),
+            "address,domain\nqa@example.com,\n,test.example.com\n"
+        );
+        assert_eq!(
+            entries[0].params(),
+            vec![
+                ("address", "qa@example.com".to_string()),
+                ("reason", "QA inbox".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_rows_should_quote_special_fields() {
+        let bounce: Bounce = serde_json::from_value(serde_json::json!({
+            "address": "jane@example.com",
+            "code": 550,
+            "error": "No such mailbox, \"jane\"",
+            "created_at": "Fri, 21 Oct 2011 11:02:55 UTC",
+        }))
+        .unwrap();
+        assert_eq!(csv_header::<Bounce>(), "address,code,error,created_at\n");
+        assert_eq!(
+            csv_row(&bounce.csv_fields()),
+            "jane@example.com,550,\"No such mailbox, \"\"jane\"\"\",\"Fri, 21 Oct 2011 11:02:55 UTC\"\n"
+        );
+    }
+
+    #[test]
+    fn unsubscribe_urls_should_escape_addresses() {
+        let client = Mailgun {
+            api_key: Default::default(),
+            domain: "example.com".to_string(),
+            region: MailgunRegion::EU,
+            ..Default::default()
+        };
+        assert_eq!(
+            client.unsubscribes_url(Some("a/b?c@example.com")).as_str(),
+            "https://api.eu.mailgun.net/v3/example.com/unsubscribes/a%2Fb%3Fc@example.com"
+        );
+    }
+}
@@ -0,0 +1,333 @@
+use crate::{build_url, get_base_url, Mailgun, MailgunRegion, SendResult};
+use reqwest::Method;
+use serde::{Deserialize, Deserializer};
+use typed_builder::TypedBuilder;
+
+const DOMAINS_ENDPOINT: &str = "domains";
+const TRACKING_ENDPOINT: &str = "tracking";
+const OPEN_ENDPOINT: &str = "open";
+const CLICK_ENDPOINT: &str = "click";
+const UNSUBSCRIBE_ENDPOINT: &str = "unsubscribe";
+const CONNECTION_ENDPOINT: &str = "connection";
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct OpenTracking {
+    pub active: bool,
+}
+
+/// Which messages get their links rewritten for click tracking.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ClickTrackingMode {
+    Disabled,
+    Enabled,
+    /// Only the HTML part, leaving plain text links untouched.
+    HtmlOnly,
+}
+
+impl ClickTrackingMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            ClickTrackingMode::Disabled => "no",
+            ClickTrackingMode::Enabled => "yes",
+            ClickTrackingMode::HtmlOnly => "htmlonly",
+        }
+    }
+}
+
+/// Mailgun reports click tracking as a boolean, or as `"htmlonly"`.
+impl<'de> Deserialize<'de> for ClickTrackingMode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Bool(bool),
+            Text(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Bool(true) => Ok(ClickTrackingMode::Enabled),
+            Raw::Bool(false) => Ok(ClickTrackingMode::Disabled),
+            Raw::Text(text) => match text.as_str() {
+                "htmlonly" => Ok(ClickTrackingMode::HtmlOnly),
+                "yes" | "true" => Ok(ClickTrackingMode::Enabled),
+                "no" | "false" => Ok(ClickTrackingMode::Disabled),
+                other => Err(serde::de::Error::unknown_variant(
+                    other,
+                    &["yes", "no", "htmlonly"],
+                )),
+            },
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ClickTracking {
+    pub active: ClickTrackingMode,
+}
+
+/// Unsubscribe links and the footer Mailgun appends to carry them.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct UnsubscribeTracking {
+    pub active: bool,
+    #[serde(default)]
+    pub html_footer: String,
+    #[serde(default)]
+    pub text_footer: String,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct TrackingSettings {
+    pub open: OpenTracking,
+    pub click: ClickTracking,
+    pub unsubscribe: UnsubscribeTracking,
+}
+
+#[derive(TypedBuilder, Debug, PartialEq, Eq, Clone)]
+pub struct UnsubscribeTrackingUpdate {
+    pub active: bool,
+    /// Footer for HTML parts; `%unsubscribe_url%` is replaced with the link.
+    #[builder(default, setter(strip_option, into))]
+    pub html_footer: Option<String>,
+    #[builder(default, setter(strip_option, into))]
+    pub text_footer: Option<String>,
+}
+
+impl UnsubscribeTrackingUpdate {
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![("active", self.active.to_string())];
+        if let Some(ref footer) = self.html_footer {
+            params.push(("html_footer", footer.clone()));
+        }
+        if let Some(ref footer) = self.text_footer {
+            params.push(("text_footer", footer.clone()));
+        }
+        params
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct OpenTrackingResponse {
+    pub message: String,
+    pub open: OpenTracking,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ClickTrackingResponse {
+    pub message: String,
+    pub click: ClickTracking,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct UnsubscribeTrackingResponse {
+    pub message: String,
+    pub unsubscribe: UnsubscribeTracking,
+}
+
+/// How Mailgun delivers a domain's messages to receiving servers.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ConnectionSettings {
+    /// Drop messages rather than deliver them without TLS.
+    pub require_tls: bool,
+    /// Accept receiving servers' certificates without checking them.
+    pub skip_verification: bool,
+}
+
+impl ConnectionSettings {
+    fn params(&self) -> [(&'static str, String); 2] {
+        [
+            ("require_tls", self.require_tls.to_string()),
+            ("skip_verification", self.skip_verification.to_string()),
+        ]
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ConnectionSettingsResponse {
+    pub message: String,
+    pub require_tls: bool,
+    pub skip_verification: bool,
+}
+
+#[derive(Deserialize)]
+struct TrackingEnvelope {
+    tracking: TrackingSettings,
+}
+
+#[derive(Deserialize)]
+struct ConnectionEnvelope {
+    connection: ConnectionSettings,
+}
+
+fn domain_url(region: &MailgunRegion, domain: &str, segments: &[&str]) -> reqwest::Url {
+    let mut path = vec![DOMAINS_ENDPOINT, domain];
+    path.extend(segments);
+    build_url(get_base_url(region), &path)
+}
+
+impl Mailgun {
+    pub fn get_tracking_settings(&self, domain: &str) -> SendResult<TrackingSettings> {
+        let url = domain_url(&self.region, domain, &[TRACKING_ENDPOINT]);
+        let envelope: TrackingEnvelope =
+            Mailgun::blocking_json(self.blocking_request(Method::GET, url)?)?;
+        Ok(envelope.tracking)
+    }
+
+    pub fn update_open_tracking(
+        &self,
+        domain: &str,
+        active: bool,
+    ) -> SendResult<OpenTrackingResponse> {
+        let url = domain_url(&self.region, domain, &[TRACKING_ENDPOINT, OPEN_ENDPOINT]);
+        Mailgun::blocking_json(
+            self.blocking_request(Method::PUT, url)?
+                .form(&[("active", active.to_string())]),
+        )
+    }
+
+    pub fn update_click_tracking(
+        &self,
+        domain: &str,
+        mode: ClickTrackingMode,
+    ) -> SendResult<ClickTrackingResponse> {
+        let url = domain_url(&self.region, domain, &[TRACKING_ENDPOINT, CLICK_ENDPOINT]);
+        Mailgun::blocking_json(
+            self.blocking_request(Method::PUT, url)?
+                .form(&[("active", mode.as_str())]),
+        )
+    }
+
+    pub fn update_unsubscribe_tracking(
+        &self,
+        domain: &str,
+        update: &UnsubscribeTrackingUpdate,
+    ) -> SendResult<UnsubscribeTrackingResponse> {
+        let url = domain_url(
+            &self.region,
+            domain,
+            &[TRACKING_ENDPOINT, UNSUBSCRIBE_ENDPOINT],
+        );
+        Mailgun::blocking_json(
+            self.blocking_request(Method::PUT, url)?
+                .form(&update.params()),
+        )
+    }
+
+    pub fn get_connection_settings(&self, domain: &str) -> SendResult<ConnectionSettings> {
+        let url = domain_url(&self.region, domain, &[CONNECTION_ENDPOINT]);
+        let envelope: ConnectionEnvelope =
+            Mailgun::blocking_json(self.blocking_request(Method::GET, url)?)?;
+        Ok(envelope.connection)
+    }
+
+    pub fn update_connection_settings(
+        &self,
+        domain: &str,
+        settings: &ConnectionSettings,
+    ) -> SendResult<ConnectionSettingsResponse> {
+        let url = domain_url(&self.region, domain, &[CONNECTION_ENDPOINT]);
+        Mailgun::blocking_json(
+            self.blocking_request(Method::PUT, url)?
+                .form(&settings.params()),
+        )
+    }
+
+    pub async fn async_get_tracking_settings(&self, domain: &str) -> SendResult<TrackingSettings> {
+        let url = domain_url(&self.region, domain, &[TRACKING_ENDPOINT]);
+        let envelope: TrackingEnvelope =
+            Mailgun::async_json(self.async_request(Method::GET, url)?).await?;
+        Ok(envelope.tracking)
+    }
+
+    pub async fn async_update_open_tracking(
+        &self,
+        domain: &str,
+        active: bool,
+    ) -> SendResult<OpenTrackingResponse> {
+        let url = domain_url(&self.region, domain, &[TRACKING_ENDPOINT, OPEN_ENDPOINT]);
+        Mailgun::async_json(
+            self.async_request(Method::PUT, url)?
+                .form(&[("active", active.to_string())]),
+        )
+        .await
+    }
+
+    pub async fn async_update_click_tracking(
+        &self,
+        domain: &str,
+        mode: ClickTrackingMode,
+    ) -> SendResult<ClickTrackingResponse> {
+        let url = domain_url(&self.region, domain, &[TRACKING_ENDPOINT, CLICK_ENDPOINT]);
+        Mailgun::async_json(
+            self.async_request(Method::PUT, url)?
+                .form(&[("active", mode.as_str())]),
+        )
+        .await
+    }
+
+    pub async fn async_update_unsubscribe_tracking(
+        &self,
+        domain: &str,
+        update: &UnsubscribeTrackingUpdate,
+    ) -> SendResult<UnsubscribeTrackingResponse> {
+        let url = domain_url(
+            &self.region,
+            domain,
+            &[TRACKING_ENDPOINT, UNSUBSCRIBE_ENDPOINT],
+        );
+        Mailgun::async_json(self.async_request(Method::PUT, url)?.form(&update.params())).await
+    }
+
+    pub async fn async_get_connection_settings(
+        &self,
+        domain: &str,
+    ) -> SendResult<ConnectionSettings> {
+        let url = domain_url(&self.region, domain, &[CONNECTION_ENDPOINT]);
+        let envelope: ConnectionEnvelope =
+            Mailgun::async_json(self.async_request(Method::GET, url)?).await?;
+        Ok(envelope.connection)
+    }
+
+    pub async fn async_update_connection_settings(
+        &self,
+        domain: &str,
+        settings: &ConnectionSettings,
+    ) -> SendResult<ConnectionSettingsResponse> {
+        let url = domain_url(&self.region, domain, &[CONNECTION_ENDPOINT]);
+        Mailgun::async_json(
+            self.async_request(Method::PUT, url)?
+                .form(&settings.params()),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracking_settings_should_parse_html_only_clicks() {
+        let envelope: TrackingEnvelope = serde_json::from_str(
+            r#"{
+                "tracking": {
+                    "click": {"active": "htmlonly"},
+                    "open": {"active": false},
+                    "unsubscribe": {
+                        "active": true,
+                        "html_footer": "<a href=\"%unsubscribe_url%\">unsubscribe</a>",
+                        "text_footer": "To unsubscribe: %unsubscribe_url%"
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let tracking = envelope.tracking;
+        assert_eq!(tracking.click.active, ClickTrackingMode::HtmlOnly);
+        assert!(!tracking.open.active);
+        assert!(tracking.unsubscribe.active);
+        assert_eq!(
+            tracking.unsubscribe.text_footer,
+            "To unsubscribe: %unsubscribe_url%"
+        );
+    }
+}
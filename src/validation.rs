@@ -0,0 +1,309 @@
+use crate::suppressions::CsvSource;
+use crate::{versioned_url, Mailgun, MailgunRegion, SendError, SendResult};
+use reqwest::Method;
+use serde::Deserialize;
+use std::io::Write;
+
+const API_VERSION: &str = "v4";
+const ADDRESS_ENDPOINT: &str = "address";
+const VALIDATE_ENDPOINT: &str = "validate";
+const BULK_ENDPOINT: &str = "bulk";
+
+/// Mailgun's verdict on whether mail to an address will be delivered.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationVerdict {
+    Deliverable,
+    Undeliverable,
+    /// Deliverable, but sending is likely to hurt your reputation.
+    DoNotSend,
+    /// The domain accepts mail for any address.
+    CatchAll,
+    Unknown,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Risk {
+    High,
+    Medium,
+    Low,
+    Unknown,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Validation {
+    pub address: String,
+    pub result: ValidationVerdict,
+    pub risk: Risk,
+    /// Why the address got its verdict, e.g. `mailbox_does_not_exist`.
+    #[serde(default)]
+    pub reason: Vec<String>,
+    /// A likely correction for a mistyped address.
+    pub did_you_mean: Option<String>,
+    #[serde(default)]
+    pub is_disposable_address: bool,
+    /// Whether the address belongs to a role such as `admin@`.
+    #[serde(default)]
+    pub is_role_address: bool,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Created,
+    Uploading,
+    Uploaded,
+    Processing,
+    Completed,
+    Failed,
+    #[serde(other)]
+    Other,
+}
+
+impl JobStatus {
+    /// Whether the job has stopped, so its results can be downloaded or it
+    /// will never produce any.
+    pub fn is_finished(self) -> bool {
+        matches!(self, JobStatus::Completed | JobStatus::Failed)
+    }
+}
+
+/// Links to a finished job's results.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct DownloadUrls {
+    pub csv: String,
+    pub json: String,
+}
+
+#[derive(Deserialize, Debug, Default, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct VerdictSummary {
+    pub deliverable: u64,
+    pub undeliverable: u64,
+    pub do_not_send: u64,
+    pub catch_all: u64,
+    pub unknown: u64,
+}
+
+#[derive(Deserialize, Debug, Default, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct RiskSummary {
+    pub high: u64,
+    pub medium: u64,
+    pub low: u64,
+    pub unknown: u64,
+}
+
+#[derive(Deserialize, Debug, Default, PartialEq, Eq, Clone)]
+#[serde(default)]
+pub struct JobSummary {
+    pub result: VerdictSummary,
+    pub risk: RiskSummary,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ValidationJob {
+    pub id: String,
+    pub status: JobStatus,
+    #[serde(default)]
+    pub quantity: u64,
+    #[serde(default)]
+    pub records_processed: u64,
+    pub created_at: Option<u64>,
+    pub download_url: Option<DownloadUrls>,
+    pub summary: Option<JobSummary>,
+}
+
+/// Which file of a finished job's results to download.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ResultFormat {
+    Csv,
+    Json,
+}
+
+impl ResultFormat {
+    fn url(self, urls: &DownloadUrls) -> &str {
+        match self {
+            ResultFormat::Csv => &urls.csv,
+            ResultFormat::Json => &urls.json,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ValidationJobResponse {
+    pub id: String,
+    pub message: String,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct DeleteJobResponse {
+    pub message: String,
+}
+
+fn validate_url(region: &MailgunRegion, job: Option<&str>) -> reqwest::Url {
+    let mut segments = vec![ADDRESS_ENDPOINT, VALIDATE_ENDPOINT];
+    if let Some(job) = job {
+        segments.push(BULK_ENDPOINT);
+        segments.push(job);
+    }
+    versioned_url(region, API_VERSION, &segments)
+}
+
+/// The results link of a finished job. Jobs without one have not completed.
+fn download_url(job: &ValidationJob, format: ResultFormat) -> SendResult<&str> {
+    job.download_url
+        .as_ref()
+        .map(|urls| format.url(urls))
+        .ok_or_else(|| SendError::JobNotFinished {
+            job: job.id.clone(),
+        })
+}
+
+impl Mailgun {
+    /// Validates a single address, e.g. before accepting it in a signup form.
+    pub fn validate(&self, address: &str) -> SendResult<Validation> {
+        Mailgun::blocking_json(
+            self.blocking_request(Method::GET, validate_url(&self.region, None))?
+                .query(&[("address", address)]),
+        )
+    }
+
+    /// Uploads a CSV with an `email` column as bulk validation job `name`.
+    pub fn create_validation_job(
+        &self,
+        name: &str,
+        csv: CsvSource,
+    ) -> SendResult<ValidationJobResponse> {
+        let form = csv.blocking_form("file")?;
+        Mailgun::blocking_json(
+            self.blocking_request(Method::POST, validate_url(&self.region, Some(name)))?
+                .multipart(form),
+        )
+    }
+
+    /// Fetches a job's progress. Poll until [`JobStatus::is_finished`].
+    pub fn get_validation_job(&self, name: &str) -> SendResult<ValidationJob> {
+        Mailgun::blocking_json(
+            self.blocking_request(Method::GET, validate_url(&self.region, Some(name)))?,
+        )
+    }
+
+    /// Writes a finished job's results to `writer` and returns how many
+    /// bytes were written.
+    pub fn download_validation_results(
+        &self,
+        job: &ValidationJob,
+        format: ResultFormat,
+        mut writer: impl Write,
+    ) -> SendResult<u64> {
+        // The link is pre-signed, so it is fetched without credentials.
+        let mut res = reqwest::blocking::get(download_url(job, format)?)?.error_for_status()?;
+        Ok(res.copy_to(&mut writer)?)
+    }
+
+    /// Cancels a running job, or deletes a finished one with its results.
+    pub fn delete_validation_job(&self, name: &str) -> SendResult<DeleteJobResponse> {
+        Mailgun::blocking_json(
+            self.blocking_request(Method::DELETE, validate_url(&self.region, Some(name)))?,
+        )
+    }
+
+    pub async fn async_validate(&self, address: &str) -> SendResult<Validation> {
+        Mailgun::async_json(
+            self.async_request(Method::GET, validate_url(&self.region, None))?
+                .query(&[("address", address)]),
+        )
+        .await
+    }
+
+    pub async fn async_create_validation_job(
+        &self,
+        name: &str,
+        csv: CsvSource,
+    ) -> SendResult<ValidationJobResponse> {
+        let form = csv.async_form("file").await?;
+        Mailgun::async_json(
+            self.async_request(Method::POST, validate_url(&self.region, Some(name)))?
+                .multipart(form),
+        )
+        .await
+    }
+
+    pub async fn async_get_validation_job(&self, name: &str) -> SendResult<ValidationJob> {
+        Mailgun::async_json(
+            self.async_request(Method::GET, validate_url(&self.region, Some(name)))?,
+        )
+        .await
+    }
+
+    /// Like [`Mailgun::download_validation_results`], but hands back the file
+    /// so the caller can store it without blocking.
+    pub async fn async_download_validation_results(
+        &self,
+        job: &ValidationJob,
+        format: ResultFormat,
+    ) -> SendResult<Vec<u8>> {
+        let res = reqwest::get(download_url(job, format)?)
+            .await?
+            .error_for_status()?;
+        Ok(res.bytes().await?.to_vec())
+    }
+
+    pub async fn async_delete_validation_job(&self, name: &str) -> SendResult<DeleteJobResponse> {
+        Mailgun::async_json(
+            self.async_request(Method::DELETE, validate_url(&self.region, Some(name)))?,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validation_should_parse_verdict_and_suggestion() {
+        let validation: Validation = serde_json::from_str(
+            r#"{
+                "address": "jane@gmial.com",
+                "did_you_mean": "jane@gmail.com",
+                "is_disposable_address": false,
+                "is_role_address": false,
+                "reason": ["no_mx"],
+                "result": "undeliverable",
+                "risk": "high"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(validation.result, ValidationVerdict::Undeliverable);
+        assert_eq!(validation.risk, Risk::High);
+        assert_eq!(validation.did_you_mean.as_deref(), Some("jane@gmail.com"));
+        assert_eq!(
+            validate_url(&MailgunRegion::EU, Some("signups")).as_str(),
+            "https://api.eu.mailgun.net/v4/address/validate/bulk/signups"
+        );
+
+        let newer: Validation = serde_json::from_str(
+            r#"{"address": "jane@example.com", "result": "greylisted", "risk": "severe"}"#,
+        )
+        .unwrap();
+        assert_eq!(newer.result, ValidationVerdict::Other);
+        assert_eq!(newer.risk, Risk::Other);
+    }
+
+    #[test]
+    fn unfinished_jobs_should_have_no_download_url() {
+        let job: ValidationJob =
+            serde_json::from_str(r#"{"id": "signups", "status": "processing"}"#).unwrap();
+        match download_url(&job, ResultFormat::Csv) {
+            Err(SendError::JobNotFinished { job }) => assert_eq!(job, "signups"),
+            other => panic!("expected an unfinished job: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use crate::events::Event;
+use crate::webhooks::{TokenStore, Verifier, VerifyError, WebhookEvent, WebhookPayload};
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+
+/// Header types [`WebhookReceiver::receive`] takes, re-exported for callers
+/// that do not depend on `http` themselves.
+pub use reqwest::header;
+
+type Handler = Arc<dyn Fn(&Event) + Send + Sync>;
+
+#[derive(Debug, Error)]
+pub enum ReceiveError {
+    #[error("unsupported webhook content type `{0}`, expected application/json")]
+    UnsupportedContentType(String),
+
+    #[error("invalid webhook body: {0}")]
+    Parse(#[from] serde_json::Error),
+
+    #[error("webhook verification failed: {0}")]
+    Verify(#[from] VerifyError),
+}
+
+impl ReceiveError {
+    /// HTTP status to answer Mailgun with. Mailgun does not retry a webhook
+    /// answered with 406, which suits requests that can never succeed.
+    pub fn status_code(&self) -> u16 {
+        match self {
+            ReceiveError::UnsupportedContentType(_) => 415,
+            ReceiveError::Parse(_) => 400,
+            ReceiveError::Verify(_) => 406,
+        }
+    }
+}
+
+/// What [`WebhookReceiver::receive`] did with a valid webhook.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Receipt {
+    /// The event was passed to this many handlers.
+    Handled { handlers: usize },
+    /// The event was delivered before and was skipped.
+    Duplicate,
+}
+
+/// Verifies, parses and deduplicates webhook requests, then dispatches the
+/// event to the handlers registered for its type. It does not depend on a web
+/// framework; with the `tower` feature it is also a `tower::Service`.
+#[derive(Clone)]
+pub struct WebhookReceiver {
+    verifier: Verifier,
+    dedup_store: Option<(Arc<dyn TokenStore>, Duration)>,
+    handlers: HashMap<WebhookEvent, Vec<Handler>>,
+    fallback: Vec<Handler>,
+}
+
+impl fmt::Debug for WebhookReceiver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WebhookReceiver")
+            .field("verifier", &self.verifier)
+            .field("dedup_store", &self.dedup_store.is_some())
+            .field("handlers", &self.handlers.keys().collect::<Vec<_>>())
+            .field("fallback", &self.fallback.len())
+            .finish()
+    }
+}
+
+impl WebhookReceiver {
+    pub fn new(verifier: Verifier) -> Self {
+        WebhookReceiver {
+            verifier,
+            dedup_store: None,
+            handlers: HashMap::new(),
+            fallback: Vec::new(),
+        }
+    }
+
+    /// Skips events whose `event-data.id` is already in `store`, so retried
+    /// deliveries are handled once. Ids are kept for `window`; Mailgun retries
+    /// failed deliveries for up to 8 hours.
+    pub fn deduplicate(mut self, store: Arc<dyn TokenStore>, window: Duration) -> Self {
+        self.dedup_store = Some((store, window));
+        self
+    }
+
+    /// Registers a handler for `event`. Several handlers may share an event.
+    pub fn on<F>(mut self, event: WebhookEvent, handler: F) -> Self
+    where
+        F: Fn(&Event) + Send + Sync + 'static,
+    {
+        self.handlers
+            .entry(event)
+            .or_default()
+            .push(Arc::new(handler));
+        self
+    }
+
+    /// Registers a handler for events no other handler was registered for.
+    pub fn on_other<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&Event) + Send + Sync + 'static,
+    {
+        self.fallback.push(Arc::new(handler));
+        self
+    }
+
+    /// Handles one webhook request given its headers and raw body.
+    pub fn receive(&self, headers: &HeaderMap, body: &[u8]) -> Result<Receipt, ReceiveError> {
+        let content_type = headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        if !content_type.starts_with("application/json") {
+            return Err(ReceiveError::UnsupportedContentType(
+                content_type.to_string(),
+            ));
+        }
+
+        let payload = WebhookPayload::from_slice(body)?;
+        self.verifier.verify(&payload.signature)?;
+
+        let event = payload.event_data;
+        if let Some((ref store, window)) = self.dedup_store {
+            if !store.insert(&event.id, SystemTime::now().checked_add(window)) {
+                return Ok(Receipt::Duplicate);
+            }
+        }
+
+        let handlers = event
+            .webhook_event()
+            .and_then(|kind| self.handlers.get(&kind))
+            .unwrap_or(&self.fallback);
+        for handler in handlers {
+            handler(&event);
+        }
+        Ok(Receipt::Handled {
+            handlers: handlers.len(),
+        })
+    }
+}
+
+#[cfg(feature = "tower")]
+mod service {
+    use super::WebhookReceiver;
+    use http_body_util::BodyExt;
+    use std::convert::Infallible;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    fn response(status: u16, body: String) -> http::Response<String> {
+        http::Response::builder()
+            .status(status)
+            .body(body)
+            .expect("status code is valid")
+    }
+
+    /// Answers 200 for handled and duplicate webhooks, and
+    /// [`super::ReceiveError::status_code`] otherwise.
+    impl<B> tower_service::Service<http::Request<B>> for WebhookReceiver
+    where
+        B: http_body::Body + Send + 'static,
+        B::Data: Send,
+    {
+        type Response = http::Response<String>;
+        type Error = Infallible;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Infallible>> + Send>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<B>) -> Self::Future {
+            let receiver = self.clone();
+            Box::pin(async move {
+                let (parts, body) = request.into_parts();
+                let body = match body.collect().await {
+                    Ok(collected) => collected.to_bytes(),
+                    Err(_) => return Ok(response(400, "cannot read body".to_string())),
+                };
+                Ok(match receiver.receive(&parts.headers, &body) {
+                    Ok(_) => response(200, "ok".to_string()),
+                    Err(err) => response(err.status_code(), err.to_string()),
+                })
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::webhooks::MemoryTokenStore;
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::UNIX_EPOCH;
+
+    fn body(key: &str, event: serde_json::Value) -> Vec<u8> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            .to_string();
+        let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes()).unwrap();
+        mac.update(timestamp.as_bytes());
+        mac.update(b"token");
+        serde_json::to_vec(&serde_json::json!({
+            "signature": {
+                "timestamp": timestamp,
+                "token": "token",
+                "signature": hex::encode(mac.finalize().into_bytes()),
+            },
+            "event-data": event,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn receiver_should_dispatch_once_per_event() {
+        let complaints = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&complaints);
+        let receiver = WebhookReceiver::new(Verifier::builder().signing_key("key").build())
+            .deduplicate(Arc::new(MemoryTokenStore::new()), Duration::from_secs(3600))
+            .on(WebhookEvent::Complained, move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            });
+
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, "application/json".parse().unwrap());
+        let body = body(
+            "key",
+            serde_json::json!({"id": "event-1", "timestamp": 1.0, "event": "complained"}),
+        );
+
+        assert_eq!(
+            receiver.receive(&headers, &body).unwrap(),
+            Receipt::Handled { handlers: 1 }
+        );
+        assert_eq!(
+            receiver.receive(&headers, &body).unwrap(),
+            Receipt::Duplicate
+        );
+        assert_eq!(complaints.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            receiver
+                .receive(&HeaderMap::new(), &body)
+                .unwrap_err()
+                .status_code(),
+            415
+        );
+    }
+}
@@ -0,0 +1,502 @@
+use crate::events::Event;
+use crate::{build_url, get_base_url, Mailgun, SendResult};
+use hmac::{Hmac, Mac};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use typed_builder::TypedBuilder;
+
+const DOMAINS_ENDPOINT: &str = "domains";
+const WEBHOOKS_ENDPOINT: &str = "webhooks";
+const TEST_ENDPOINT: &str = "test";
+
+/// How far a webhook timestamp may drift from the local clock by default.
+pub const DEFAULT_TOLERANCE: Duration = Duration::from_secs(5 * 60);
+
+/// Events a domain webhook can be registered for.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    Accepted,
+    Delivered,
+    PermanentFail,
+    TemporaryFail,
+    Opened,
+    Clicked,
+    Unsubscribed,
+    Complained,
+}
+
+impl WebhookEvent {
+    pub const ALL: [WebhookEvent; 8] = [
+        WebhookEvent::Accepted,
+        WebhookEvent::Delivered,
+        WebhookEvent::PermanentFail,
+        WebhookEvent::TemporaryFail,
+        WebhookEvent::Opened,
+        WebhookEvent::Clicked,
+        WebhookEvent::Unsubscribed,
+        WebhookEvent::Complained,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WebhookEvent::Accepted => "accepted",
+            WebhookEvent::Delivered => "delivered",
+            WebhookEvent::PermanentFail => "permanent_fail",
+            WebhookEvent::TemporaryFail => "temporary_fail",
+            WebhookEvent::Opened => "opened",
+            WebhookEvent::Clicked => "clicked",
+            WebhookEvent::Unsubscribed => "unsubscribed",
+            WebhookEvent::Complained => "complained",
+        }
+    }
+}
+
+impl fmt::Display for WebhookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct Webhook {
+    #[serde(default)]
+    pub urls: Vec<String>,
+}
+
+/// Every webhook of a domain, keyed by event name. Keys are kept as strings
+/// so legacy event names Mailgun may still report do not break parsing.
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct DomainWebhooks {
+    pub webhooks: HashMap<String, Webhook>,
+}
+
+impl DomainWebhooks {
+    /// URLs registered for `event`, empty if there are none.
+    pub fn urls(&self, event: WebhookEvent) -> &[String] {
+        self.webhooks
+            .get(event.as_str())
+            .map(|webhook| webhook.urls.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct WebhookResponse {
+    pub message: String,
+    #[serde(default)]
+    pub webhook: Webhook,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct WebhookTestResponse {
+    pub code: u16,
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+struct WebhookEnvelope {
+    webhook: Webhook,
+}
+
+fn url_params(urls: &[&str]) -> Vec<(&'static str, String)> {
+    urls.iter().map(|url| ("url", url.to_string())).collect()
+}
+
+/// The `signature` object Mailgun includes in every webhook body.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Signature {
+    pub timestamp: String,
+    pub token: String,
+    pub signature: String,
+}
+
+/// The JSON body Mailgun posts to a domain webhook.
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+pub struct WebhookPayload {
+    pub signature: Signature,
+    #[serde(rename = "event-data")]
+    pub event_data: Event,
+}
+
+impl WebhookPayload {
+    pub fn from_slice(body: &[u8]) -> serde_json::Result<Self> {
+        serde_json::from_slice(body)
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum VerifyError {
+    #[error("webhook signature does not match")]
+    InvalidSignature,
+
+    #[error("webhook timestamp `{0}` is not a unix timestamp")]
+    InvalidTimestamp(String),
+
+    #[error("webhook timestamp is {age:?} away from the local clock")]
+    StaleTimestamp { age: Duration },
+
+    #[error("webhook token was already used")]
+    ReplayedToken,
+}
+
+/// Remembers webhook tokens so replayed deliveries can be rejected.
+pub trait TokenStore: Send + Sync {
+    /// Records `token`, returning `false` if it is still recorded from
+    /// before. The token must be kept until `expires_at` by the local clock,
+    /// or for good if `None`.
+    ///
+    /// [`Verifier`] passes the time its timestamp check starts rejecting the
+    /// token anyway, or `None` when that check is disabled.
+    fn insert(&self, token: &str, expires_at: Option<SystemTime>) -> bool;
+}
+
+/// A [`TokenStore`] kept in process memory, suited to a single instance.
+///
+/// Expired tokens are purged on insert. Tokens without an expiry, e.g. from a
+/// verifier whose tolerance is disabled, are kept for as long as the store.
+#[derive(Debug, Default)]
+pub struct MemoryTokenStore {
+    seen: Mutex<HashMap<String, Option<SystemTime>>>,
+}
+
+impl MemoryTokenStore {
+    pub fn new() -> Self {
+        MemoryTokenStore::default()
+    }
+
+    fn insert_at(&self, token: &str, expires_at: Option<SystemTime>, now: SystemTime) -> bool {
+        let mut seen = self.seen.lock().unwrap();
+        seen.retain(|_, expires_at| match expires_at {
+            Some(expires_at) => *expires_at >= now,
+            None => true,
+        });
+        if seen.contains_key(token) {
+            return false;
+        }
+        seen.insert(token.to_string(), expires_at);
+        true
+    }
+}
+
+impl TokenStore for MemoryTokenStore {
+    fn insert(&self, token: &str, expires_at: Option<SystemTime>) -> bool {
+        self.insert_at(token, expires_at, SystemTime::now())
+    }
+}
+
+/// Checks webhook signatures with a domain's HTTP webhook signing key.
+#[derive(TypedBuilder, Clone)]
+pub struct Verifier {
+    #[builder(setter(into))]
+    signing_key: String,
+    /// Largest accepted difference between the webhook timestamp and now.
+    /// `None` disables the check, so the token store has to keep every token
+    /// for good to reject replays.
+    #[builder(default = Some(DEFAULT_TOLERANCE))]
+    tolerance: Option<Duration>,
+    #[builder(default, setter(strip_option))]
+    token_store: Option<Arc<dyn TokenStore>>,
+}
+
+impl fmt::Debug for Verifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Verifier")
+            .field("signing_key", &"[REDACTED]")
+            .field("tolerance", &self.tolerance)
+            .field("token_store", &self.token_store.is_some())
+            .finish()
+    }
+}
+
+impl Verifier {
+    pub fn verify(&self, signature: &Signature) -> Result<(), VerifyError> {
+        self.verify_at(signature, SystemTime::now())
+    }
+
+    fn verify_at(&self, signature: &Signature, now: SystemTime) -> Result<(), VerifyError> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.signing_key.as_bytes())
+            .expect("hmac accepts keys of any length");
+        mac.update(signature.timestamp.as_bytes());
+        mac.update(signature.token.as_bytes());
+        let expected =
+            hex::decode(&signature.signature).map_err(|_| VerifyError::InvalidSignature)?;
+        mac.verify_slice(&expected)
+            .map_err(|_| VerifyError::InvalidSignature)?;
+
+        let timestamp: u64 = signature
+            .timestamp
+            .parse()
+            .map_err(|_| VerifyError::InvalidTimestamp(signature.timestamp.clone()))?;
+
+        let mut expires_at = None;
+        if let Some(tolerance) = self.tolerance {
+            let sent_at = UNIX_EPOCH
+                .checked_add(Duration::from_secs(timestamp))
+                .ok_or_else(|| VerifyError::InvalidTimestamp(signature.timestamp.clone()))?;
+            let age = match now.duration_since(sent_at) {
+                Ok(age) => age,
+                Err(err) => err.duration(),
+            };
+            if age > tolerance {
+                return Err(VerifyError::StaleTimestamp { age });
+            }
+            // Replays after this are stale, so the token need not be kept.
+            expires_at = sent_at.checked_add(tolerance);
+        }
+
+        if let Some(ref store) = self.token_store {
+            if !store.insert(&signature.token, expires_at) {
+                return Err(VerifyError::ReplayedToken);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Verifies `signature` with `signing_key` in constant time, rejecting
+/// timestamps more than [`DEFAULT_TOLERANCE`] away from now. Build a
+/// [`Verifier`] to change the tolerance or to reject replayed tokens.
+pub fn verify(signing_key: &str, signature: &Signature) -> Result<(), VerifyError> {
+    Verifier::builder()
+        .signing_key(signing_key)
+        .build()
+        .verify(signature)
+}
+
+impl Mailgun {
+    fn webhooks_url(&self, segments: &[&str]) -> reqwest::Url {
+        let mut path = vec![DOMAINS_ENDPOINT, self.domain.as_str(), WEBHOOKS_ENDPOINT];
+        path.extend(segments);
+        build_url(get_base_url(&self.region), &path)
+    }
+
+    pub fn list_webhooks(&self) -> SendResult<DomainWebhooks> {
+        let url = self.webhooks_url(&[]);
+        Mailgun::blocking_json(self.blocking_request(Method::GET, url)?)
+    }
+
+    pub fn get_webhook(&self, event: WebhookEvent) -> SendResult<Webhook> {
+        let url = self.webhooks_url(&[event.as_str()]);
+        let envelope: WebhookEnvelope =
+            Mailgun::blocking_json(self.blocking_request(Method::GET, url)?)?;
+        Ok(envelope.webhook)
+    }
+
+    /// Registers `urls` for an event that has no webhook yet. Mailgun accepts
+    /// up to three URLs per event.
+    pub fn create_webhook(
+        &self,
+        event: WebhookEvent,
+        urls: &[&str],
+    ) -> SendResult<WebhookResponse> {
+        let url = self.webhooks_url(&[]);
+        let mut params = url_params(urls);
+        params.push(("id", event.as_str().to_string()));
+        Mailgun::blocking_json(self.blocking_request(Method::POST, url)?.form(&params))
+    }
+
+    /// Replaces the URLs of an existing webhook.
+    pub fn update_webhook(
+        &self,
+        event: WebhookEvent,
+        urls: &[&str],
+    ) -> SendResult<WebhookResponse> {
+        let url = self.webhooks_url(&[event.as_str()]);
+        Mailgun::blocking_json(
+            self.blocking_request(Method::PUT, url)?
+                .form(&url_params(urls)),
+        )
+    }
+
+    pub fn delete_webhook(&self, event: WebhookEvent) -> SendResult<WebhookResponse> {
+        let url = self.webhooks_url(&[event.as_str()]);
+        Mailgun::blocking_json(self.blocking_request(Method::DELETE, url)?)
+    }
+
+    /// Asks Mailgun to post a sample `event` payload to `url` and reports the
+    /// status code the endpoint answered with.
+    pub fn test_webhook(&self, event: WebhookEvent, url: &str) -> SendResult<WebhookTestResponse> {
+        let endpoint = self.webhooks_url(&[event.as_str(), TEST_ENDPOINT]);
+        Mailgun::blocking_json(
+            self.blocking_request(Method::PUT, endpoint)?
+                .form(&[("url", url)]),
+        )
+    }
+
+    pub async fn async_list_webhooks(&self) -> SendResult<DomainWebhooks> {
+        let url = self.webhooks_url(&[]);
+        Mailgun::async_json(self.async_request(Method::GET, url)?).await
+    }
+
+    pub async fn async_get_webhook(&self, event: WebhookEvent) -> SendResult<Webhook> {
+        let url = self.webhooks_url(&[event.as_str()]);
+        let envelope: WebhookEnvelope =
+            Mailgun::async_json(self.async_request(Method::GET, url)?).await?;
+        Ok(envelope.webhook)
+    }
+
+    pub async fn async_create_webhook(
+        &self,
+        event: WebhookEvent,
+        urls: &[&str],
+    ) -> SendResult<WebhookResponse> {
+        let url = self.webhooks_url(&[]);
+        let mut params = url_params(urls);
+        params.push(("id", event.as_str().to_string()));
+        Mailgun::async_json(self.async_request(Method::POST, url)?.form(&params)).await
+    }
+
+    pub async fn async_update_webhook(
+        &self,
+        event: WebhookEvent,
+        urls: &[&str],
+    ) -> SendResult<WebhookResponse> {
+        let url = self.webhooks_url(&[event.as_str()]);
+        Mailgun::async_json(
+            self.async_request(Method::PUT, url)?
+                .form(&url_params(urls)),
+        )
+        .await
+    }
+
+    pub async fn async_delete_webhook(&self, event: WebhookEvent) -> SendResult<WebhookResponse> {
+        let url = self.webhooks_url(&[event.as_str()]);
+        Mailgun::async_json(self.async_request(Method::DELETE, url)?).await
+    }
+
+    pub async fn async_test_webhook(
+        &self,
+        event: WebhookEvent,
+        url: &str,
+    ) -> SendResult<WebhookTestResponse> {
+        let endpoint = self.webhooks_url(&[event.as_str(), TEST_ENDPOINT]);
+        Mailgun::async_json(
+            self.async_request(Method::PUT, endpoint)?
+                .form(&[("url", url)]),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed(key: &str, timestamp: &str, token: &str) -> Signature {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes()).unwrap();
+        mac.update(timestamp.as_bytes());
+        mac.update(token.as_bytes());
+        Signature {
+            timestamp: timestamp.to_string(),
+            token: token.to_string(),
+            signature: hex::encode(mac.finalize().into_bytes()),
+        }
+    }
+
+    #[test]
+    fn verifier_should_check_signature_age_and_replays() {
+        // The token store expires tokens by the real clock.
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let now = UNIX_EPOCH + Duration::from_secs(secs);
+        let verifier = Verifier::builder()
+            .signing_key("key")
+            .token_store(Arc::new(MemoryTokenStore::new()) as Arc<dyn TokenStore>)
+            .build();
+
+        let signature = signed("key", &secs.to_string(), "token-1");
+        assert_eq!(verifier.verify_at(&signature, now), Ok(()));
+        assert_eq!(
+            verifier.verify_at(&signature, now),
+            Err(VerifyError::ReplayedToken)
+        );
+
+        let forged = signed("other-key", &secs.to_string(), "token-2");
+        assert_eq!(
+            verifier.verify_at(&forged, now),
+            Err(VerifyError::InvalidSignature)
+        );
+
+        let stale = signed("key", &(secs - 10_000).to_string(), "token-3");
+        assert_eq!(
+            verifier.verify_at(&stale, now),
+            Err(VerifyError::StaleTimestamp {
+                age: Duration::from_secs(10_000)
+            })
+        );
+
+        let far_future = signed("key", &u64::MAX.to_string(), "token-4");
+        assert_eq!(
+            verifier.verify_at(&far_future, now),
+            Err(VerifyError::InvalidTimestamp(u64::MAX.to_string()))
+        );
+
+        let lenient = Verifier::builder()
+            .signing_key("key")
+            .tolerance(None)
+            .build();
+        assert_eq!(lenient.verify_at(&stale, now), Ok(()));
+    }
+
+    #[test]
+    fn token_store_should_keep_tokens_until_they_expire() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let expires_at = Some(now + Duration::from_secs(3600));
+        let store = MemoryTokenStore::new();
+        assert!(store.insert_at("token-1", expires_at, now));
+        assert!(store.insert_at("token-2", None, now));
+
+        let later = now + Duration::from_secs(3000);
+        assert!(!store.insert_at("token-1", expires_at, later));
+        let expired = now + Duration::from_secs(4000);
+        assert!(store.insert_at("token-1", expires_at, expired));
+        assert!(!store.insert_at("token-2", None, expired));
+    }
+
+    #[test]
+    fn replays_should_be_rejected_for_as_long_as_the_tolerance() {
+        let now = SystemTime::now();
+        let sent_at = now - Duration::from_secs(30 * 60);
+        let timestamp = sent_at.duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let signature = signed("key", &timestamp.to_string(), "token-1");
+
+        for tolerance in [Some(Duration::from_secs(3600)), None].iter() {
+            let verifier = Verifier::builder()
+                .signing_key("key")
+                .tolerance(*tolerance)
+                .token_store(Arc::new(MemoryTokenStore::new()) as Arc<dyn TokenStore>)
+                .build();
+            assert_eq!(verifier.verify_at(&signature, now), Ok(()));
+            assert_eq!(
+                verifier.verify_at(&signature, now),
+                Err(VerifyError::ReplayedToken)
+            );
+        }
+    }
+
+    #[test]
+    fn domain_webhooks_should_tolerate_legacy_events() {
+        let webhooks: DomainWebhooks = serde_json::from_value(serde_json::json!({
+            "webhooks": {
+                "permanent_fail": {"urls": ["https://example.com/a", "https://example.com/b"]},
+                "bounce": {"url": "https://example.com/legacy"},
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            webhooks.urls(WebhookEvent::PermanentFail),
+            ["https://example.com/a", "https://example.com/b"]
+        );
+        assert!(webhooks.urls(WebhookEvent::Delivered).is_empty());
+    }
+}